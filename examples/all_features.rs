@@ -1,5 +1,3 @@
-use std::io::stderr;
-
 use ray1week::prelude::*;
 
 use ray1week::{
@@ -11,6 +9,8 @@ use ray1week::{
 };
 
 fn main() -> Result<(), RenderError> {
+    let seed = 7;
+    let mut rng = fastrand::Rng::with_seed(seed);
     let mut boxes1 = Collection::new();
     let ground = Lambertian::new(Colour::new(0.48, 0.83, 0.53));
     let boxes_per_side = 20;
@@ -21,7 +21,7 @@ fn main() -> Result<(), RenderError> {
             let z0 = -1000.0 + j as f64 * w;
             let y0 = 0.0;
             let x1 = x0 + w;
-            let y1 = fastrand::f64() * 100.0 + 1.0;
+            let y1 = rng.f64() * 100.0 + 1.0;
             let z1 = z0 + w;
             boxes1.add(Cube::new(
                 Point3::new(x0, y0, z0),
@@ -74,7 +74,7 @@ fn main() -> Result<(), RenderError> {
     let emat =
         Lambertian::from_texture(ImageTexture::new("examples/resources/earthmap.jpg").unwrap());
     world.add(Sphere::new(Point3::new(400.0, 200.0, 400.0), 100.0, emat));
-    let pertext = NoiseTexture::plain(0.2);
+    let pertext = NoiseTexture::plain(0.2, seed);
     world.add(Sphere::new(
         Point3::new(220.0, 280.0, 300.0),
         80.0,
@@ -85,7 +85,11 @@ fn main() -> Result<(), RenderError> {
     let white = Lambertian::new(0.73 * Colour::WHITE);
     let ns = 1000;
     for _ in 0..ns {
-        boxes2.add(Sphere::new(Point3::random(0.0, 165.0), 10.0, white.clone()));
+        boxes2.add(Sphere::new(
+            Point3::random(&mut rng, 0.0, 165.0),
+            10.0,
+            white.clone(),
+        ));
     }
 
     world.add(Translate::new(
@@ -104,10 +108,6 @@ fn main() -> Result<(), RenderError> {
         ..Camera::default()
     };
 
-    let renderer = cam.renderer(10, 50);
-    renderer.render_to_file(
-        &mut world,
-        "examples/output/all_features_test.png",
-        &mut stderr(),
-    )
+    let renderer = cam.renderer(10, 50).with_seed(seed);
+    renderer.render_to_file(&mut world, "examples/output/all_features_test.png")
 }
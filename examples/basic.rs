@@ -1,5 +1,3 @@
-use std::io::stderr;
-
 use ray1week::prelude::*;
 
 use ray1week::material::{Dielectric, Lambertian, Metal};
@@ -38,5 +36,5 @@ fn main() -> Result<(), RenderError> {
 
     // Render to file
     let renderer = camera.renderer(100, 10);
-    renderer.render_to_file(&mut world, "examples/output/basic.png", &mut stderr())
+    renderer.render_to_file(&mut world, "examples/output/basic.png")
 }
@@ -1,5 +1,3 @@
-use std::io::stderr;
-
 use ray1week::prelude::*;
 
 use ray1week::{
@@ -39,9 +37,5 @@ fn main() -> Result<(), RenderError> {
 
     let renderer = cam.renderer(100, 50);
 
-    renderer.render_to_file(
-        &mut world,
-        "examples/output/checkered_spheres.png",
-        &mut stderr(),
-    )
+    renderer.render_to_file(&mut world, "examples/output/checkered_spheres.png")
 }
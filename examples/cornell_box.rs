@@ -1,5 +1,3 @@
-use std::io::stderr;
-
 use ray1week::prelude::*;
 
 use ray1week::{
@@ -83,5 +81,5 @@ fn main() -> Result<(), RenderError> {
     };
 
     let renderer = cam.renderer(1000, 50);
-    renderer.render_to_file(&mut world, "examples/output/cornell_box.png", &mut stderr())
+    renderer.render_to_file(&mut world, "examples/output/cornell_box.png")
 }
@@ -1,5 +1,3 @@
-use std::io::stderr;
-
 use ray1week::prelude::*;
 
 use ray1week::{
@@ -48,5 +46,5 @@ fn main() -> Result<(), RenderError> {
 
     // Render to file
     let renderer = camera.renderer(50, 10);
-    renderer.render_to_file(&mut world, "examples/output/dof.png", &mut stderr())
+    renderer.render_to_file(&mut world, "examples/output/dof.png")
 }
@@ -1,5 +1,3 @@
-use std::io::stderr;
-
 use ray1week::prelude::*;
 
 use ray1week::{material::Lambertian, objects::Sphere, texture::ImageTexture};
@@ -25,5 +23,5 @@ fn main() -> Result<(), RenderError> {
 
     let renderer = cam.renderer(100, 50);
 
-    renderer.render_to_file(&mut world, "examples/output/globe.png", &mut stderr())
+    renderer.render_to_file(&mut world, "examples/output/globe.png")
 }
@@ -1,4 +1,4 @@
-use std::{collections::HashMap, io::stderr};
+use std::collections::HashMap;
 
 use ray1week::prelude::*;
 
@@ -12,6 +12,8 @@ use ray1week::{
 const BOUNDARY: i32 = 11;
 
 fn main() -> Result<(), RenderError> {
+    let seed = 7;
+    let mut rng = fastrand::Rng::with_seed(seed);
     let mut world = Scene::new();
     let mut materials: Vec<Material> = Vec::new();
     let checker =
@@ -29,7 +31,7 @@ fn main() -> Result<(), RenderError> {
     let mut centers = Vec::new();
     let mut moving = HashMap::new();
 
-    make_random_spheres(&mut materials, &mut centers, &mut moving);
+    make_random_spheres(&mut rng, &mut materials, &mut centers, &mut moving);
 
     for (idx, &center) in centers.iter().enumerate() {
         let a = center.x.floor() as i32;
@@ -60,38 +62,35 @@ fn main() -> Result<(), RenderError> {
         ..Camera::default()
     };
 
-    let renderer = cam.renderer(100, 50);
-    renderer.render_to_file(&mut world, "examples/output/motion_blur.png", &mut stderr())
+    let renderer = cam.renderer(100, 50).with_seed(seed);
+    renderer.render_to_file(&mut world, "examples/output/motion_blur.png")
 }
 
 fn make_random_spheres(
+    rng: &mut fastrand::Rng,
     materials: &mut Vec<Material>,
     centers: &mut Vec<Point3>,
     moving: &mut HashMap<(i32, i32), Point3>,
 ) {
     for a in -BOUNDARY..BOUNDARY {
         for b in -BOUNDARY..BOUNDARY {
-            let choose_mat = fastrand::f64();
-            let center = Point3::new(
-                a as f64 + 0.9 * fastrand::f64(),
-                0.2,
-                b as f64 + 0.9 * fastrand::f64(),
-            );
+            let choose_mat = rng.f64();
+            let center = Point3::new(a as f64 + 0.9 * rng.f64(), 0.2, b as f64 + 0.9 * rng.f64());
 
             if (center - Point3::new(4.0, 0.2, 0.0)).length() > 0.9 {
                 if choose_mat < 0.8 {
                     // diffuse
-                    let albedo = Colour::random().attenuate(&Colour::random());
+                    let albedo = Colour::random(rng).attenuate(&Colour::random(rng));
                     materials.push(Lambertian::new(albedo));
-                    moving.insert((a, b), center + Vec3::new(0.0, 0.5 * fastrand::f64(), 0.0));
+                    moving.insert((a, b), center + Vec3::new(0.0, 0.5 * rng.f64(), 0.0));
                 } else if choose_mat < 0.95 {
                     // metal
                     let albedo = Colour::new(
-                        fastrand::f64() / 2.0 + 0.5,
-                        fastrand::f64() / 2.0 + 0.5,
-                        fastrand::f64() / 2.0 + 0.5,
+                        rng.f64() / 2.0 + 0.5,
+                        rng.f64() / 2.0 + 0.5,
+                        rng.f64() / 2.0 + 0.5,
                     );
-                    let fuzz = 0.5 * fastrand::f64();
+                    let fuzz = 0.5 * rng.f64();
                     materials.push(Metal::new(albedo, fuzz));
                 } else {
                     // glass
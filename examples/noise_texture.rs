@@ -1,19 +1,18 @@
-use std::io::stderr;
-
 use ray1week::prelude::*;
 
 use ray1week::{material::Lambertian, objects::Sphere, texture::NoiseTexture};
 
 fn main() -> Result<(), RenderError> {
+    let seed = 7;
     let mut world = Scene::new();
 
-    let ground = NoiseTexture::plain(4.0);
+    let ground = NoiseTexture::plain(4.0, seed);
     let ground = Lambertian::from_texture(ground);
 
-    let marble = NoiseTexture::marble(4.0);
+    let marble = NoiseTexture::marble(4.0, seed);
 
     let marble = Lambertian::from_texture(marble);
-    let turbulence = NoiseTexture::turbulence(1.0, 7);
+    let turbulence = NoiseTexture::turbulence(1.0, 7, seed);
     let turbulence = Lambertian::from_texture(turbulence);
 
     world.add(Sphere::new(Point3::new(0.0, -1000.0, 0.0), 1000.0, ground));
@@ -31,11 +30,7 @@ fn main() -> Result<(), RenderError> {
         ..Camera::default()
     };
 
-    let renderer = cam.renderer(100, 50);
+    let renderer = cam.renderer(100, 50).with_seed(seed);
 
-    renderer.render_to_file(
-        &mut world,
-        "examples/output/perlin_spheres.png",
-        &mut stderr(),
-    )
+    renderer.render_to_file(&mut world, "examples/output/perlin_spheres.png")
 }
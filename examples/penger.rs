@@ -1,5 +1,3 @@
-use std::io::stderr;
-
 use ray1week::effects::BackFaceCulling;
 use ray1week::prelude::*;
 use ray1week::{objects::WavefrontObj, prelude::Scene, render::Camera};
@@ -19,7 +17,7 @@ fn main() -> Result<(), RenderError> {
         ..Camera::default()
     };
     let renderer = cam.renderer(50, 50);
-    let img = renderer.render_with_filter(&mut world, BackFaceCulling, &mut stderr());
+    let img = renderer.render_with_filter(&mut world, BackFaceCulling)?;
     img.save("examples/output/penger.png")?;
     Ok(())
 }
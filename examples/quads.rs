@@ -1,5 +1,3 @@
-use std::io::stderr;
-
 use ray1week::prelude::*;
 
 use ray1week::{material::Lambertian, objects::Quad};
@@ -58,5 +56,5 @@ fn main() -> Result<(), RenderError> {
     };
 
     let renderer = cam.renderer(100, 50);
-    renderer.render_to_file(&mut world, "examples/output/quads.png", &mut stderr())
+    renderer.render_to_file(&mut world, "examples/output/quads.png")
 }
@@ -1,5 +1,3 @@
-use std::io::stderr;
-
 use ray1week::prelude::*;
 
 use ray1week::{
@@ -8,6 +6,8 @@ use ray1week::{
 };
 
 fn main() -> Result<(), RenderError> {
+    let seed = 7;
+    let mut rng = fastrand::Rng::with_seed(seed);
     let mut world = Scene::new();
     let mut materials: Vec<Material> = Vec::new();
     let ground_material = Lambertian::new(Colour::new(0.5, 0.5, 0.5));
@@ -23,7 +23,7 @@ fn main() -> Result<(), RenderError> {
 
     let mut centers = Vec::new();
 
-    make_random_spheres(&mut materials, &mut centers);
+    make_random_spheres(&mut rng, &mut materials, &mut centers);
 
     for (idx, &center) in centers.iter().enumerate() {
         world.add(Sphere::new(center, 0.2, materials[idx].clone()));
@@ -43,37 +43,33 @@ fn main() -> Result<(), RenderError> {
         ..Camera::default()
     };
 
-    let renderer = cam.renderer(50, 50);
-    renderer.render_to_file(
-        &mut world,
-        "examples/output/random_spheres.png",
-        &mut stderr(),
-    )
+    let renderer = cam.renderer(50, 50).with_seed(seed);
+    renderer.render_to_file(&mut world, "examples/output/random_spheres.png")
 }
 
-fn make_random_spheres(materials: &mut Vec<Material>, centers: &mut Vec<Point3>) {
+fn make_random_spheres(
+    rng: &mut fastrand::Rng,
+    materials: &mut Vec<Material>,
+    centers: &mut Vec<Point3>,
+) {
     for a in -11..11 {
         for b in -11..11 {
-            let choose_mat = fastrand::f64();
-            let center = Point3::new(
-                a as f64 + 0.9 * fastrand::f64(),
-                0.2,
-                b as f64 + 0.9 * fastrand::f64(),
-            );
+            let choose_mat = rng.f64();
+            let center = Point3::new(a as f64 + 0.9 * rng.f64(), 0.2, b as f64 + 0.9 * rng.f64());
 
             if (center - Point3::new(4.0, 0.2, 0.0)).length() > 0.9 {
                 if choose_mat < 0.8 {
                     // diffuse
-                    let albedo = Colour::random().attenuate(&Colour::random());
+                    let albedo = Colour::random(rng).attenuate(&Colour::random(rng));
                     materials.push(Lambertian::new(albedo));
                 } else if choose_mat < 0.95 {
                     // metal
                     let albedo = Colour::new(
-                        fastrand::f64() / 2.0 + 0.5,
-                        fastrand::f64() / 2.0 + 0.5,
-                        fastrand::f64() / 2.0 + 0.5,
+                        rng.f64() / 2.0 + 0.5,
+                        rng.f64() / 2.0 + 0.5,
+                        rng.f64() / 2.0 + 0.5,
                     );
-                    let fuzz = 0.5 * fastrand::f64();
+                    let fuzz = 0.5 * rng.f64();
                     materials.push(Metal::new(albedo, fuzz));
                 } else {
                     // glass
@@ -1,5 +1,3 @@
-use std::io::stderr;
-
 use ray1week::prelude::*;
 
 use ray1week::{
@@ -9,10 +7,11 @@ use ray1week::{
 };
 
 fn main() -> Result<(), RenderError> {
+    let seed = 7;
     let mut world = Scene::new();
-    let marble = NoiseTexture::marble(4.0);
+    let marble = NoiseTexture::marble(4.0, seed);
     let marble = Lambertian::from_texture(marble);
-    let ground = NoiseTexture::plain(1.0);
+    let ground = NoiseTexture::plain(1.0, seed);
     let ground = Lambertian::from_texture(ground);
 
     world.add(Sphere::new(Point3::new(0.0, -1000.0, 0.0), 1000.0, ground));
@@ -37,10 +36,6 @@ fn main() -> Result<(), RenderError> {
         ..Camera::default()
     };
 
-    let renderer = cam.renderer(100, 50);
-    renderer.render_to_file(
-        &mut world,
-        "examples/output/diffuse_light.png",
-        &mut stderr(),
-    )
+    let renderer = cam.renderer(100, 50).with_seed(seed);
+    renderer.render_to_file(&mut world, "examples/output/diffuse_light.png")
 }
@@ -1,5 +1,3 @@
-use std::io::stderr;
-
 use ray1week::material::Metal;
 use ray1week::prelude::*;
 use ray1week::{objects::WavefrontObj, prelude::Scene, render::Camera};
@@ -18,5 +16,5 @@ fn main() -> Result<(), RenderError> {
         ..Camera::default()
     };
     let renderer = cam.renderer(50, 50);
-    renderer.render_to_file(&mut world, "examples/output/utah_teapot.png", &mut stderr())
+    renderer.render_to_file(&mut world, "examples/output/utah_teapot.png")
 }
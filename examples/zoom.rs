@@ -1,5 +1,3 @@
-use std::io::stderr;
-
 use ray1week::prelude::*;
 
 use ray1week::{
@@ -52,8 +50,8 @@ fn main() -> Result<(), RenderError> {
     // Render to file
     let renderer = camera.renderer(50, 10);
     renderer
-        .render_to_file(&mut world, "examples/output/zoom_out.png", &mut stderr())
+        .render_to_file(&mut world, "examples/output/zoom_out.png")
         .unwrap();
     let renderer = zoom.renderer(50, 10);
-    renderer.render_to_file(&mut world, "examples/output/zoom_in.png", &mut stderr())
+    renderer.render_to_file(&mut world, "examples/output/zoom_in.png")
 }
@@ -0,0 +1,180 @@
+use crate::checkpoint::{Decoder, Encoder};
+use crate::error::RenderError;
+
+#[derive(Debug, Clone, Copy)]
+pub struct AdaptiveSampling {
+    pub min_samples: usize,
+    pub max_samples: usize,
+    pub threshold: f64,
+}
+
+impl Default for AdaptiveSampling {
+    fn default() -> Self {
+        Self {
+            min_samples: 16,
+            max_samples: 1024,
+            threshold: 0.02,
+        }
+    }
+}
+
+impl AdaptiveSampling {
+    pub(crate) fn validate(&self) -> Result<(), RenderError> {
+        if self.min_samples == 0 || self.min_samples > self.max_samples {
+            return Err(RenderError::InvalidAdaptiveSampling {
+                min_samples: self.min_samples,
+                max_samples: self.max_samples,
+            });
+        }
+        Ok(())
+    }
+
+    pub(crate) fn needs_sample(&self, estimate: &RunningVariance) -> bool {
+        estimate.n < self.min_samples
+            || (estimate.n < self.max_samples && estimate.relative_error() > self.threshold)
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct RunningVariance {
+    n: usize,
+    mean: f64,
+    m2: f64,
+}
+
+impl RunningVariance {
+    pub fn add(&mut self, x: f64) {
+        // Welford's online algorithm.
+        self.n += 1;
+        let delta = x - self.mean;
+        self.mean += delta / self.n as f64;
+        self.m2 += delta * (x - self.mean);
+    }
+
+    pub fn encode(&self, encoder: &mut Encoder) {
+        encoder.u64(self.n as u64);
+        encoder.f64(self.mean);
+        encoder.f64(self.m2);
+    }
+
+    pub fn decode(decoder: &mut Decoder) -> Result<Self, RenderError> {
+        Ok(Self {
+            n: decoder.u64()? as usize,
+            mean: decoder.f64()?,
+            m2: decoder.f64()?,
+        })
+    }
+
+    pub fn count(&self) -> usize {
+        self.n
+    }
+
+    pub fn relative_error(&self) -> f64 {
+        if self.n < 2 {
+            return f64::INFINITY;
+        }
+        let variance = self.m2 / (self.n - 1) as f64;
+        (variance / self.n as f64).sqrt() / (self.mean.abs() + 1e-3)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aov::Aov;
+    use crate::colour::Colour;
+    use crate::linalg::Point3;
+    use crate::material::Lambertian;
+    use crate::objects::Sphere;
+    use crate::progress::ProgressEvent;
+    use crate::render::Camera;
+    use crate::scene::Scene;
+    use crate::texture::SolidColour;
+
+    fn sample_counts(world: &mut Scene, cam: &Camera, adaptive: AdaptiveSampling) -> Vec<usize> {
+        let frame = cam
+            .renderer(1, 8)
+            .with_progress(|_: &ProgressEvent| {})
+            .with_aovs(&[Aov::SampleCount])
+            .with_adaptive_sampling(adaptive)
+            .render_frame(world)
+            .unwrap();
+        frame.aovs[&Aov::SampleCount]
+            .pixels()
+            .map(|p| p.0[0] as usize)
+            .collect()
+    }
+
+    #[test]
+    fn invalid_sample_ranges_are_rejected() {
+        let mut world = Scene::new();
+        world.add(Sphere::new(
+            Point3::new(0.0, 0.0, -1.0),
+            0.5,
+            Lambertian::new(Colour::new(0.5, 0.5, 0.5)),
+        ));
+        let cam = Camera {
+            image_width: 16,
+            ..Camera::default()
+        };
+        for (min_samples, max_samples) in [(0, 8), (16, 8)] {
+            let result = cam
+                .renderer(1, 8)
+                .with_progress(|_: &ProgressEvent| {})
+                .with_adaptive_sampling(AdaptiveSampling {
+                    min_samples,
+                    max_samples,
+                    threshold: 0.02,
+                })
+                .render_frame(&mut world);
+            assert!(matches!(
+                result,
+                Err(RenderError::InvalidAdaptiveSampling { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn flat_images_stop_at_min_samples() {
+        // The only object is behind the camera, so every pixel sees the same background.
+        let mut world = Scene::new();
+        world.add(Sphere::new(
+            Point3::new(0.0, 0.0, 5.0),
+            0.5,
+            Lambertian::new(Colour::new(0.5, 0.5, 0.5)),
+        ));
+        let cam = Camera {
+            image_width: 16,
+            background: SolidColour::new(Colour::new(0.3, 0.5, 0.7)),
+            ..Camera::default()
+        };
+        let adaptive = AdaptiveSampling {
+            min_samples: 4,
+            max_samples: 64,
+            threshold: 0.02,
+        };
+        let counts = sample_counts(&mut world, &cam, adaptive);
+        assert!(counts.iter().all(|&n| n == 4));
+    }
+
+    #[test]
+    fn noisy_images_reach_max_samples() {
+        let mut world = Scene::new();
+        world.add(Sphere::new(
+            Point3::new(0.0, 0.0, -1.0),
+            0.5,
+            Lambertian::new(Colour::new(0.5, 0.5, 0.5)),
+        ));
+        let cam = Camera {
+            image_width: 16,
+            ..Camera::default()
+        };
+        let adaptive = AdaptiveSampling {
+            min_samples: 4,
+            max_samples: 32,
+            threshold: 1e-6,
+        };
+        let counts = sample_counts(&mut world, &cam, adaptive);
+        assert!(counts.iter().all(|&n| n == 32));
+    }
+}
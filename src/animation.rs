@@ -0,0 +1,272 @@
+use std::fs;
+use std::ops::{Add, Mul, Range, Sub};
+use std::path::PathBuf;
+
+use crate::error::RenderError;
+use crate::linalg::Point3;
+use crate::render::{Camera, Renderer};
+use crate::scene::Scene;
+
+pub trait Keyframeable:
+    Copy + Add<Output = Self> + Sub<Output = Self> + Mul<f64, Output = Self>
+{
+}
+
+impl<T> Keyframeable for T where T: Copy + Add<Output = T> + Sub<Output = T> + Mul<f64, Output = T> {}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Interpolation {
+    Step,
+    #[default]
+    Linear,
+    Smooth,
+    CatmullRom,
+}
+
+#[derive(Debug, Clone)]
+pub struct Keyframes<T> {
+    keys: Vec<(f64, T)>,
+    interpolation: Interpolation,
+}
+
+impl<T: Keyframeable> Keyframes<T> {
+    // Starts from a first key, so there is always a value to return.
+    pub fn new(interpolation: Interpolation, time: f64, value: T) -> Self {
+        Self {
+            keys: vec![(time, value)],
+            interpolation,
+        }
+    }
+
+    pub fn constant(value: T) -> Self {
+        Self::new(Interpolation::Step, 0.0, value)
+    }
+
+    pub fn key(mut self, time: f64, value: T) -> Self {
+        let index = self.keys.partition_point(|(t, _)| *t <= time);
+        self.keys.insert(index, (time, value));
+        self
+    }
+
+    pub fn at(&self, time: f64) -> T {
+        let n = self.keys.len();
+        let i = self.keys.partition_point(|(t, _)| *t <= time);
+        if i == 0 {
+            return self.keys[0].1;
+        }
+        if i == n {
+            return self.keys[n - 1].1;
+        }
+        let (t1, p1) = self.keys[i - 1];
+        let (t2, p2) = self.keys[i];
+        let u = (time - t1) / (t2 - t1);
+        match self.interpolation {
+            Interpolation::Step => p1,
+            Interpolation::Linear => p1 + (p2 - p1) * u,
+            Interpolation::Smooth => p1 + (p2 - p1) * (u * u * (3.0 - 2.0 * u)),
+            Interpolation::CatmullRom => {
+                let p0 = if i >= 2 { self.keys[i - 2].1 } else { p1 };
+                let p3 = if i + 1 < n { self.keys[i + 1].1 } else { p2 };
+                let a = p1 * 2.0;
+                let b = p2 - p0;
+                let c = p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3;
+                let d = p1 * 3.0 - p0 - p2 * 3.0 + p3;
+                (a + b * u + c * (u * u) + d * (u * u * u)) * 0.5
+            }
+        }
+    }
+
+    // Each segment as a cubic Bézier curve, split into `pieces` parts. Every part stays inside
+    // the convex hull of its control points, which bounds overshoot between keys.
+    pub(crate) fn hulls(&self, pieces: usize) -> Vec<[T; 4]> {
+        if self.keys.len() == 1 {
+            return vec![[self.keys[0].1; 4]];
+        }
+        let mut hulls = Vec::new();
+        for i in 1..self.keys.len() {
+            let (p1, p2) = (self.keys[i - 1].1, self.keys[i].1);
+            let segment = match self.interpolation {
+                Interpolation::Step => [p1; 4],
+                Interpolation::Linear => [p1, lerp(p1, p2, 1.0 / 3.0), lerp(p1, p2, 2.0 / 3.0), p2],
+                Interpolation::Smooth => [p1, p1, p2, p2],
+                Interpolation::CatmullRom => {
+                    let p0 = if i >= 2 { self.keys[i - 2].1 } else { p1 };
+                    let p3 = self.keys.get(i + 1).map_or(p2, |k| k.1);
+                    [
+                        p1,
+                        p1 + (p2 - p0) * (1.0 / 6.0),
+                        p2 - (p3 - p1) * (1.0 / 6.0),
+                        p2,
+                    ]
+                }
+            };
+            let mut rest = segment;
+            for k in 0..pieces {
+                // Cut off the next part, scaled to what is left of the segment.
+                let (piece, tail) = split(rest, 1.0 / (pieces - k) as f64);
+                hulls.push(piece);
+                rest = tail;
+            }
+        }
+        hulls
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AnimatedCamera {
+    pub camera: Camera,
+    pub lookfrom: Option<Keyframes<Point3>>,
+    pub lookat: Option<Keyframes<Point3>>,
+    pub vfov: Option<Keyframes<f64>>,
+    pub focus_dist: Option<Keyframes<f64>>,
+}
+
+impl AnimatedCamera {
+    pub fn new(camera: Camera) -> Self {
+        Self {
+            camera,
+            lookfrom: None,
+            lookat: None,
+            vfov: None,
+            focus_dist: None,
+        }
+    }
+
+    pub fn with_lookfrom(mut self, keys: Keyframes<Point3>) -> Self {
+        self.lookfrom = Some(keys);
+        self
+    }
+
+    pub fn with_lookat(mut self, keys: Keyframes<Point3>) -> Self {
+        self.lookat = Some(keys);
+        self
+    }
+
+    pub fn with_vfov(mut self, keys: Keyframes<f64>) -> Self {
+        self.vfov = Some(keys);
+        self
+    }
+
+    pub fn with_focus_dist(mut self, keys: Keyframes<f64>) -> Self {
+        self.focus_dist = Some(keys);
+        self
+    }
+
+    pub fn at(&self, time: f64) -> Camera {
+        let camera = &self.camera;
+        let value =
+            |keys: &Option<Keyframes<f64>>, default| keys.as_ref().map_or(default, |k| k.at(time));
+        let point = |keys: &Option<Keyframes<Point3>>, default| {
+            keys.as_ref().map_or(default, |k| k.at(time))
+        };
+        Camera {
+            lookfrom: point(&self.lookfrom, camera.lookfrom),
+            lookat: point(&self.lookat, camera.lookat),
+            vfov: value(&self.vfov, camera.vfov),
+            focus_dist: value(&self.focus_dist, camera.focus_dist),
+            ..camera.clone()
+        }
+    }
+
+    // The camera for a frame, with the shutter interval given in fractions of the frame duration.
+    pub fn frame(&self, frame: usize, fps: f64) -> Camera {
+        let time = frame as f64 / fps;
+        Camera {
+            shutter_open: time + self.camera.shutter_open / fps,
+            shutter_close: time + self.camera.shutter_close / fps,
+            ..self.at(time)
+        }
+    }
+
+    pub fn render_sequence<R>(
+        &self,
+        world: &Scene,
+        sequence: &Sequence,
+        renderer: R,
+    ) -> Result<(), RenderError>
+    where
+        R: Fn(&Camera) -> Renderer,
+    {
+        fs::create_dir_all(&sequence.directory)?;
+        let mut prepared = None;
+        for frame in sequence.frames.clone() {
+            let renderer = renderer(&self.frame(frame, sequence.fps));
+            // Animated objects are bounded over their whole motion, so the hierarchy stays valid.
+            let scene = prepared.get_or_insert_with(|| renderer.prepare(world));
+            let image = renderer.render_prepared(world, scene)?.image;
+            renderer.save(&image, sequence.path(frame))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Sequence {
+    pub directory: PathBuf,
+    pub frames: Range<usize>,
+    pub fps: f64,
+    pub extension: String,
+}
+
+impl Sequence {
+    pub fn new(directory: impl Into<PathBuf>, frames: Range<usize>) -> Self {
+        Self {
+            directory: directory.into(),
+            frames,
+            fps: 24.0,
+            extension: "png".to_string(),
+        }
+    }
+
+    pub fn path(&self, frame: usize) -> PathBuf {
+        self.directory
+            .join(format!("frame_{frame:04}.{}", self.extension))
+    }
+}
+
+fn lerp<T: Keyframeable>(a: T, b: T, t: f64) -> T {
+    a + (b - a) * t
+}
+
+// De Casteljau's algorithm, splitting a cubic Bézier curve at `t`.
+fn split<T: Keyframeable>([a, b, c, d]: [T; 4], t: f64) -> ([T; 4], [T; 4]) {
+    let (ab, bc, cd) = (lerp(a, b, t), lerp(b, c, t), lerp(c, d, t));
+    let (abc, bcd) = (lerp(ab, bc, t), lerp(bc, cd, t));
+    let abcd = lerp(abc, bcd, t);
+    ([a, ab, abc, abcd], [abcd, bcd, cd, d])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hulls_contain_catmull_rom_overshoot() {
+        let keys = Keyframes::new(Interpolation::CatmullRom, 0.0, 0.0)
+            .key(1.0, 0.0)
+            .key(2.0, 10.0)
+            .key(3.0, 10.0);
+        let hulls = keys.hulls(4);
+        let lo = hulls
+            .iter()
+            .flatten()
+            .copied()
+            .fold(f64::INFINITY, f64::min);
+        let hi = hulls
+            .iter()
+            .flatten()
+            .copied()
+            .fold(f64::NEG_INFINITY, f64::max);
+        let values: Vec<f64> = (0..=3000).map(|k| keys.at(k as f64 / 1000.0)).collect();
+        assert!(values.iter().any(|v| !(0.0..=10.0).contains(v)));
+        assert!(values.iter().all(|&v| (lo..=hi).contains(&v)));
+    }
+
+    #[test]
+    fn single_key_is_constant() {
+        let keys = Keyframes::constant(3.0);
+        assert_eq!(keys.at(-1.0), 3.0);
+        assert_eq!(keys.at(5.0), 3.0);
+        assert_eq!(keys.hulls(8), vec![[3.0; 4]]);
+    }
+}
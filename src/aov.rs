@@ -0,0 +1,179 @@
+use crate::{
+    checkpoint::{Decoder, Encoder},
+    colour::Colour,
+    error::RenderError,
+    integrator::Radiance,
+    linalg::{Point3, Vec3},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Aov {
+    Depth,
+    Normal,
+    Albedo,
+    ObjectId,
+    MaterialId,
+    Emission,
+    Direct,
+    Indirect,
+    SampleCount,
+}
+
+impl Aov {
+    pub const ALL: [Aov; 9] = [
+        Aov::Depth,
+        Aov::Normal,
+        Aov::Albedo,
+        Aov::ObjectId,
+        Aov::MaterialId,
+        Aov::Emission,
+        Aov::Direct,
+        Aov::Indirect,
+        Aov::SampleCount,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Aov::Depth => "depth",
+            Aov::Normal => "normal",
+            Aov::Albedo => "albedo",
+            Aov::ObjectId => "object_id",
+            Aov::MaterialId => "material_id",
+            Aov::Emission => "emission",
+            Aov::Direct => "direct",
+            Aov::Indirect => "indirect",
+            Aov::SampleCount => "sample_count",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SurfaceInfo {
+    pub p: Point3,
+    pub normal: Vec3,
+    pub albedo: Colour,
+    pub object_id: usize,
+    pub material_id: usize,
+}
+
+pub(crate) struct AovPixel {
+    sums: Vec<Colour>,
+    samples: usize,
+    hits: usize,
+}
+
+impl AovPixel {
+    pub fn new(n: usize) -> Self {
+        Self {
+            sums: vec![Colour::BLACK; n],
+            samples: 0,
+            hits: 0,
+        }
+    }
+
+    pub fn add(
+        &mut self,
+        aovs: &[Aov],
+        radiance: &Radiance,
+        surface: Option<&SurfaceInfo>,
+        depth: impl Fn(Point3) -> f64,
+    ) {
+        for (sum, aov) in self.sums.iter_mut().zip(aovs) {
+            match (aov, surface) {
+                (Aov::Depth, Some(s)) => *sum += depth(s.p) * Colour::WHITE,
+                (Aov::Normal, Some(s)) => *sum += Colour::from(s.normal),
+                (Aov::Albedo, Some(s)) => *sum += s.albedo,
+                // Identifiers cannot be blended, so the first hit decides.
+                (Aov::ObjectId, Some(s)) if self.hits == 0 => {
+                    *sum = s.object_id as f64 * Colour::WHITE
+                }
+                (Aov::MaterialId, Some(s)) if self.hits == 0 => {
+                    *sum = s.material_id as f64 * Colour::WHITE
+                }
+                (Aov::Emission, _) => *sum += radiance.emitted,
+                (Aov::Direct, _) => *sum += radiance.direct,
+                (Aov::Indirect, _) => *sum += radiance.indirect,
+                _ => {}
+            }
+        }
+        self.samples += 1;
+        self.hits += surface.is_some() as usize;
+    }
+
+    pub fn encode(&self, encoder: &mut Encoder) {
+        encoder.u64(self.samples as u64);
+        encoder.u64(self.hits as u64);
+        for &sum in self.sums.iter() {
+            encoder.colour(sum);
+        }
+    }
+
+    pub fn decode(decoder: &mut Decoder, n: usize) -> Result<Self, RenderError> {
+        let samples = decoder.u64()? as usize;
+        let hits = decoder.u64()? as usize;
+        let sums = (0..n).map(|_| decoder.colour()).collect::<Result<_, _>>()?;
+        Ok(Self {
+            sums,
+            samples,
+            hits,
+        })
+    }
+
+    pub fn resolve(&self, aovs: &[Aov]) -> Vec<Colour> {
+        self.sums
+            .iter()
+            .zip(aovs)
+            .map(|(&sum, aov)| match aov {
+                Aov::Depth if self.hits == 0 => f64::INFINITY * Colour::WHITE,
+                Aov::Depth | Aov::Normal | Aov::Albedo if self.hits > 0 => sum / self.hits as f64,
+                Aov::Emission | Aov::Direct | Aov::Indirect => sum / self.samples.max(1) as f64,
+                Aov::SampleCount => self.samples as f64 * Colour::WHITE,
+                _ => sum,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::objects::Sphere;
+    use crate::progress::ProgressEvent;
+    use crate::render::Camera;
+    use crate::scene::Scene;
+
+    #[test]
+    fn surface_aovs_describe_the_first_hit() {
+        let mut world = Scene::new();
+        world.add(Sphere::new(
+            Point3::new(0.0, 0.0, -1.0),
+            0.5,
+            Lambertian::new(Colour::new(0.2, 0.4, 0.6)),
+        ));
+        let cam = Camera {
+            image_width: 16,
+            ..Camera::default()
+        };
+        let frame = cam
+            .renderer(4, 4)
+            .with_progress(|_: &ProgressEvent| {})
+            .with_aovs(&[Aov::Normal, Aov::Albedo, Aov::Depth, Aov::ObjectId])
+            .render_frame(&mut world)
+            .unwrap();
+        let pixel = |aov, x, y| frame.aovs[&aov].get_pixel(x, y).0;
+
+        // The centre of the image looks straight at the front of the sphere.
+        let normal = pixel(Aov::Normal, 8, 4);
+        assert!(normal[0].abs() < 0.2 && normal[1].abs() < 0.2 && normal[2] > 0.9);
+        assert_eq!(pixel(Aov::Albedo, 8, 4), [0.2, 0.4, 0.6]);
+        assert!((pixel(Aov::Depth, 8, 4)[0] - 0.5).abs() < 0.01);
+        assert_eq!(pixel(Aov::ObjectId, 8, 4), [1.0; 3]);
+
+        // The corners only see the sky.
+        assert_eq!(pixel(Aov::Normal, 0, 0), [0.0; 3]);
+        assert_eq!(pixel(Aov::Albedo, 0, 0), [0.0; 3]);
+        assert_eq!(pixel(Aov::Depth, 0, 0), [f32::INFINITY; 3]);
+        assert_eq!(pixel(Aov::ObjectId, 0, 0), [0.0; 3]);
+    }
+}
@@ -5,8 +5,11 @@ const DELTA: f64 = 0.0001;
 
 use crate::{
     linalg::{Point3, Vec3},
+    material::Material,
     objects::{Collection, HitRecord, Hittable, Interval},
     ray::Ray,
+    sampler::SampleStream,
+    statistics::{self, Counter},
 };
 
 #[derive(Debug, Default, Clone, Copy)]
@@ -153,6 +156,8 @@ pub struct BVHNode<'a> {
     left: Arc<dyn Hittable + 'a>,
     right: Arc<dyn Hittable + 'a>,
     bbox: AaBb,
+    nodes: usize,
+    primitives: u64,
 }
 
 impl<'a> BVHNode<'a> {
@@ -169,6 +174,8 @@ impl<'a> BVHNode<'a> {
                 left: obj.clone(),
                 right: obj.clone(),
                 bbox,
+                nodes: 1,
+                primitives: 1,
             };
         } else if objects.len() == 2 {
             let bbox = AaBb::enclosing(&objects[0].bbox(), &objects[1].bbox());
@@ -176,6 +183,8 @@ impl<'a> BVHNode<'a> {
                 left: objects[0].clone(),
                 right: objects[1].clone(),
                 bbox,
+                nodes: 1,
+                primitives: 2,
             };
         }
 
@@ -187,15 +196,31 @@ impl<'a> BVHNode<'a> {
         });
         let mid = objects.len() / 2;
         let mut right = objects.split_off(mid);
-        let left = Arc::new(Self::new(objects));
-        let right = Arc::new(Self::new(&mut right));
-        Self { left, right, bbox }
+        let left = Self::new(objects);
+        let right = Self::new(&mut right);
+        Self {
+            nodes: 1 + left.nodes + right.nodes,
+            left: Arc::new(left),
+            right: Arc::new(right),
+            bbox,
+            primitives: 0,
+        }
+    }
+
+    pub fn nodes(&self) -> usize {
+        self.nodes
     }
 }
 
 impl<'a> Hittable for BVHNode<'a> {
-    fn hit(&self, ray: &Ray, range: Interval) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, range: Interval) -> Option<HitRecord<'_>> {
+        statistics::add(Counter::BvhNodesTested, 1);
         self.bbox.hit(ray, range)?;
+        // Only leaves hold primitives; a leaf with a single object stores it on both sides.
+        statistics::add(Counter::PrimitivesTested, self.primitives);
+        if self.primitives == 1 {
+            return self.left.hit(ray, range);
+        }
         let mut max = range.max;
         let hit_left = self.left.hit(ray, range);
         if let Some(ref rec_left) = hit_left {
@@ -207,10 +232,10 @@ impl<'a> Hittable for BVHNode<'a> {
     fn bbox(&self) -> AaBb {
         self.bbox
     }
-    fn pdf_value(&self, _origin: &Point3, _direction: &Vec3) -> f64 {
+    fn pdf_value(&self, _origin: &Point3, _direction: &Vec3, _time: f64) -> f64 {
         panic!("Asked for PDF on a bounding box!")
     }
-    fn random(&self, _origin: &Point3) -> Vec3 {
+    fn random(&self, _origin: &Point3, _time: f64, _samples: &mut SampleStream) -> Vec3 {
         panic!("Asked for PDF on a bounding box!")
     }
     fn lights(&self) -> Collection {
@@ -218,4 +243,9 @@ impl<'a> Hittable for BVHNode<'a> {
         res.extend(self.right.lights());
         res
     }
+    fn materials(&self) -> Vec<Material> {
+        let mut res = self.left.materials();
+        res.extend(self.right.materials());
+        res
+    }
 }
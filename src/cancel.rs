@@ -0,0 +1,112 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::aov::Aov;
+    use crate::colour::Colour;
+    use crate::error::RenderError;
+    use crate::linalg::Point3;
+    use crate::material::Lambertian;
+    use crate::objects::Sphere;
+    use crate::progress::ProgressEvent;
+    use crate::render::{Camera, Frame};
+    use crate::scene::Scene;
+
+    fn scene() -> (Scene, Camera) {
+        let mut world = Scene::new();
+        world.add(Sphere::new(
+            Point3::new(0.0, 0.0, -1.0),
+            0.5,
+            Lambertian::new(Colour::new(0.5, 0.5, 0.5)),
+        ));
+        let cam = Camera {
+            image_width: 160,
+            ..Camera::default()
+        };
+        (world, cam)
+    }
+
+    // The sample count AOV has to account for every camera path that was traced.
+    fn sample_counts(frame: &Frame) -> Vec<u64> {
+        let counts = &frame.aovs[&Aov::SampleCount];
+        let counts: Vec<u64> = counts.pixels().map(|p| p.0[0] as u64).collect();
+        assert_eq!(counts.iter().sum::<u64>(), frame.statistics.paths);
+        for (count, pixel) in counts.iter().zip(frame.image.pixels()) {
+            if *count == 0 {
+                assert_eq!(pixel.0, [0.0; 3]);
+            }
+        }
+        counts
+    }
+
+    #[test]
+    fn time_budgets_return_what_was_traced() {
+        let (mut world, cam) = scene();
+        let frame = cam
+            .renderer(16, 5)
+            .with_progress(|_: &ProgressEvent| {})
+            .with_time_budget(Duration::ZERO)
+            .render_frame(&mut world)
+            .unwrap();
+        assert!(!frame.complete);
+        assert!(sample_counts(&frame).iter().all(|&count| count == 0));
+
+        let frame = cam
+            .renderer(1_000_000, 5)
+            .with_progress(|_: &ProgressEvent| {})
+            .with_time_budget(Duration::from_millis(50))
+            .render_frame(&mut world)
+            .unwrap();
+        assert!(!frame.complete);
+        // The whole image is refined one sample at a time, so no pixel gets ahead by more than one.
+        let counts = sample_counts(&frame);
+        let (min, max) = (counts.iter().min().unwrap(), counts.iter().max().unwrap());
+        assert!(*max > 0 && max - min <= 1, "{min}..{max}");
+    }
+
+    #[test]
+    fn cancelled_renders_return_a_partial_frame() {
+        let (mut world, cam) = scene();
+        let token = CancellationToken::new();
+        let cancel = token.clone();
+        // Cancel as soon as the first of six blocks is done, leaving the others to stop early.
+        let result = cam
+            .renderer(16, 5)
+            .with_progress(move |event: &ProgressEvent| {
+                if let ProgressEvent::BlockFinished { .. } = event {
+                    cancel.cancel();
+                }
+            })
+            .with_cancellation(token)
+            .render_frame(&mut world);
+        let Err(RenderError::Cancelled(frame)) = result else {
+            panic!("render was not cancelled");
+        };
+        assert!(!frame.complete);
+        assert_eq!(frame.image.dimensions(), (160, 90));
+        let counts = sample_counts(&frame);
+        assert!(counts.contains(&16));
+        assert!(counts.iter().any(|&count| count < 16));
+    }
+}
@@ -0,0 +1,333 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::colour::Colour;
+use crate::error::RenderError;
+use crate::linalg::Vec3;
+
+const MAGIC: &[u8; 8] = b"R1WCKPT1";
+
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    pub path: PathBuf,
+    pub interval: Duration,
+    pub resume: bool,
+    // Checked along with the camera and the layout of the scene. Bump it when changing anything
+    // else, like materials, textures, the background or the integrator, to start over.
+    pub version: u64,
+}
+
+impl Checkpoint {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            interval: Duration::from_secs(60),
+            resume: true,
+            version: 0,
+        }
+    }
+}
+
+pub(crate) struct CheckpointState<'a> {
+    checkpoint: &'a Checkpoint,
+    fingerprint: u64,
+    inner: Mutex<Snapshots>,
+}
+
+struct Snapshots {
+    blocks: Vec<Option<Vec<u8>>>,
+    last_save: Instant,
+}
+
+impl<'a> CheckpointState<'a> {
+    pub fn new(checkpoint: &'a Checkpoint, fingerprint: u64, n_blocks: usize) -> Self {
+        Self {
+            checkpoint,
+            fingerprint,
+            inner: Mutex::new(Snapshots {
+                blocks: vec![None; n_blocks],
+                last_save: Instant::now(),
+            }),
+        }
+    }
+
+    pub fn file(&self) -> &Path {
+        &self.checkpoint.path
+    }
+
+    pub fn path(&self) -> String {
+        self.checkpoint.path.display().to_string()
+    }
+
+    pub fn load(&self) -> Result<Vec<Option<Vec<u8>>>, RenderError> {
+        let mut inner = self.inner.lock().unwrap();
+        if !self.checkpoint.resume || !self.checkpoint.path.exists() {
+            return Ok(inner.blocks.clone());
+        }
+        let data = fs::read(&self.checkpoint.path)?;
+        let mut decoder = Decoder::new(&data, self.path());
+        if decoder.bytes(MAGIC.len())? != MAGIC {
+            return Err(RenderError::InvalidCheckpoint(self.path()));
+        }
+        if decoder.u64()? != self.fingerprint {
+            return Err(RenderError::CheckpointMismatch(self.path()));
+        }
+        if decoder.u64()? as usize != inner.blocks.len() {
+            return Err(RenderError::InvalidCheckpoint(self.path()));
+        }
+        for block in inner.blocks.iter_mut() {
+            *block = match decoder.u64()? {
+                0 => None,
+                len => Some(decoder.bytes(len as usize)?.to_vec()),
+            };
+        }
+        Ok(inner.blocks.clone())
+    }
+
+    // Whether the interval has elapsed since the last save, so blocks are worth snapshotting.
+    pub fn due(&self) -> bool {
+        self.inner.lock().unwrap().last_save.elapsed() >= self.checkpoint.interval
+    }
+
+    pub fn record(&self, index: usize, snapshot: Vec<u8>) {
+        self.inner.lock().unwrap().blocks[index] = Some(snapshot);
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        self.write(&inner.blocks)?;
+        inner.last_save = Instant::now();
+        Ok(())
+    }
+
+    fn write(&self, blocks: &[Option<Vec<u8>>]) -> std::io::Result<()> {
+        // Write to a temporary file first, so a crash never leaves a truncated checkpoint.
+        let tmp = self.checkpoint.path.with_extension("tmp");
+        let mut file = BufWriter::new(File::create(&tmp)?);
+        file.write_all(MAGIC)?;
+        file.write_all(&self.fingerprint.to_le_bytes())?;
+        file.write_all(&(blocks.len() as u64).to_le_bytes())?;
+        for block in blocks {
+            let block = block.as_deref().unwrap_or_default();
+            file.write_all(&(block.len() as u64).to_le_bytes())?;
+            file.write_all(block)?;
+        }
+        file.flush()?;
+        drop(file);
+        fs::rename(tmp, &self.checkpoint.path)
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct Encoder(Vec<u8>);
+
+impl Encoder {
+    pub fn u64(&mut self, value: u64) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn f64(&mut self, value: f64) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn colour(&mut self, value: Colour) {
+        for c in value.components() {
+            self.f64(c);
+        }
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+pub(crate) struct Decoder<'a> {
+    data: &'a [u8],
+    path: String,
+}
+
+impl<'a> Decoder<'a> {
+    pub fn new(data: &'a [u8], path: String) -> Self {
+        Self { data, path }
+    }
+
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8], RenderError> {
+        if self.data.len() < n {
+            return Err(RenderError::InvalidCheckpoint(self.path.clone()));
+        }
+        let (head, tail) = self.data.split_at(n);
+        self.data = tail;
+        Ok(head)
+    }
+
+    pub fn u64(&mut self) -> Result<u64, RenderError> {
+        Ok(u64::from_le_bytes(self.bytes(8)?.try_into().unwrap()))
+    }
+
+    pub fn f64(&mut self) -> Result<f64, RenderError> {
+        Ok(f64::from_le_bytes(self.bytes(8)?.try_into().unwrap()))
+    }
+
+    pub fn colour(&mut self) -> Result<Colour, RenderError> {
+        Ok(Colour::from_components([
+            self.f64()?,
+            self.f64()?,
+            self.f64()?,
+        ]))
+    }
+}
+
+// FNV-1a, chosen over the std hasher because its output is stable across releases.
+pub(crate) struct Fingerprint(u64);
+
+impl Default for Fingerprint {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Fingerprint {
+    pub fn bytes(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    pub fn u64(&mut self, value: u64) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    pub fn f64(&mut self, value: f64) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    pub fn vec3(&mut self, value: Vec3) {
+        for c in [value.x, value.y, value.z] {
+            self.f64(c);
+        }
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::linalg::Point3;
+    use crate::material::Lambertian;
+    use crate::objects::Sphere;
+    use crate::progress::ProgressEvent;
+    use crate::render::Camera;
+    use crate::scene::Scene;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("ray1week-{}-{name}.ckpt", std::process::id()))
+    }
+
+    fn blocks() -> Vec<Option<Vec<u8>>> {
+        vec![Some(vec![1, 2, 3]), None, Some(vec![4; 20])]
+    }
+
+    #[test]
+    fn saved_blocks_are_resumed() {
+        let checkpoint = Checkpoint {
+            interval: Duration::from_secs(3600),
+            ..Checkpoint::new(temp_path("resume"))
+        };
+        let state = CheckpointState::new(&checkpoint, 42, 3);
+        for (index, block) in blocks().into_iter().enumerate() {
+            if let Some(block) = block {
+                state.record(index, block);
+            }
+        }
+        // Recording stays in memory until saved.
+        assert!(!state.due());
+        assert!(!checkpoint.path.exists());
+        state.save().unwrap();
+
+        assert_eq!(
+            CheckpointState::new(&checkpoint, 42, 3).load().unwrap(),
+            blocks()
+        );
+        let fresh = Checkpoint {
+            resume: false,
+            ..checkpoint.clone()
+        };
+        assert_eq!(
+            CheckpointState::new(&fresh, 42, 3).load().unwrap(),
+            vec![None; 3]
+        );
+        fs::remove_file(&checkpoint.path).unwrap();
+    }
+
+    #[test]
+    fn mismatched_checkpoints_are_rejected() {
+        let checkpoint = Checkpoint::new(temp_path("mismatch"));
+        let state = CheckpointState::new(&checkpoint, 42, 3);
+        state.record(0, vec![1, 2, 3]);
+        state.save().unwrap();
+
+        assert!(matches!(
+            CheckpointState::new(&checkpoint, 43, 3).load(),
+            Err(RenderError::CheckpointMismatch(_))
+        ));
+        assert!(matches!(
+            CheckpointState::new(&checkpoint, 42, 4).load(),
+            Err(RenderError::InvalidCheckpoint(_))
+        ));
+        let data = fs::read(&checkpoint.path).unwrap();
+        fs::write(&checkpoint.path, &data[..data.len() - 1]).unwrap();
+        assert!(matches!(
+            CheckpointState::new(&checkpoint, 42, 3).load(),
+            Err(RenderError::InvalidCheckpoint(_))
+        ));
+        fs::remove_file(&checkpoint.path).unwrap();
+    }
+
+    #[test]
+    fn renders_resume_from_their_checkpoint() {
+        let mut world = Scene::new();
+        world.add(Sphere::new(
+            Point3::new(0.0, 0.0, -1.0),
+            0.5,
+            Lambertian::new(Colour::new(0.5, 0.5, 0.5)),
+        ));
+        let cam = Camera {
+            image_width: 24,
+            ..Camera::default()
+        };
+        let path = temp_path("render");
+        let renderer = |samples_per_pixel| {
+            cam.renderer(samples_per_pixel, 5)
+                .with_progress(|_: &ProgressEvent| {})
+                .with_checkpoint(Checkpoint::new(&path))
+        };
+        let first = renderer(4).render_frame(&mut world).unwrap();
+        let resumed = renderer(4).render_frame(&mut world).unwrap();
+        assert_eq!(resumed.statistics.rays, 0);
+        assert_eq!(resumed.image, first.image);
+
+        assert!(matches!(
+            renderer(16).render_frame(&mut world),
+            Err(RenderError::CheckpointMismatch(_))
+        ));
+        let bumped = cam
+            .renderer(4, 5)
+            .with_progress(|_: &ProgressEvent| {})
+            .with_checkpoint(Checkpoint {
+                version: 1,
+                ..Checkpoint::new(&path)
+            });
+        assert!(matches!(
+            bumped.render_frame(&mut world),
+            Err(RenderError::CheckpointMismatch(_))
+        ));
+        fs::remove_file(&path).unwrap();
+    }
+}
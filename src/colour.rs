@@ -2,6 +2,7 @@ use std::ops::{Add, AddAssign, Div, Mul, Sub, SubAssign};
 
 use image::Rgb;
 
+use crate::display::DisplayTransform;
 use crate::linalg::Vec3;
 
 #[derive(Debug, Clone, Copy)]
@@ -28,9 +29,7 @@ impl Colour {
     }
 
     pub fn ppm(&self) -> String {
-        let r = (256.0 * self.r.clamp(0.0, 0.999).sqrt()) as usize;
-        let g = (256.0 * self.g.clamp(0.0, 0.999).sqrt()) as usize;
-        let b = (256.0 * self.b.clamp(0.0, 0.999).sqrt()) as usize;
+        let [r, g, b] = Rgb::from(self).0.map(|c| (256.0 * c.min(0.999)) as usize);
         format!("{r} {g} {b}")
     }
 
@@ -42,22 +41,52 @@ impl Colour {
         }
     }
 
-    pub fn random() -> Self {
+    pub fn linear(&self) -> Rgb<f32> {
+        Rgb([self.r as f32, self.g as f32, self.b as f32])
+    }
+
+    pub(crate) fn components(&self) -> [f64; 3] {
+        [self.r, self.g, self.b]
+    }
+
+    pub(crate) fn from_components([r, g, b]: [f64; 3]) -> Self {
+        Self { r, g, b }
+    }
+
+    pub fn luminance(&self) -> f64 {
+        0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b
+    }
+
+    pub fn max_component(&self) -> f64 {
+        self.r.max(self.g).max(self.b)
+    }
+
+    pub fn length_squared(&self) -> f64 {
+        self.r * self.r + self.g * self.g + self.b * self.b
+    }
+
+    pub fn random(rng: &mut fastrand::Rng) -> Self {
         Self {
-            r: fastrand::f64(),
-            g: fastrand::f64(),
-            b: fastrand::f64(),
+            r: rng.f64(),
+            g: rng.f64(),
+            b: rng.f64(),
         }
     }
 }
 
 impl From<&Colour> for Rgb<f32> {
     fn from(value: &Colour) -> Self {
-        Self([
-            value.r.clamp(0.0, 1.0).sqrt() as f32,
-            value.g.clamp(0.0, 1.0).sqrt() as f32,
-            value.b.clamp(0.0, 1.0).sqrt() as f32,
-        ])
+        DisplayTransform::default().encode(*value)
+    }
+}
+
+impl From<&Rgb<f32>> for Colour {
+    fn from(value: &Rgb<f32>) -> Self {
+        Self {
+            r: value.0[0] as f64,
+            g: value.0[1] as f64,
+            b: value.0[2] as f64,
+        }
     }
 }
 
@@ -0,0 +1,252 @@
+use image::{Rgb, Rgb32FImage};
+use rayon::iter::{IndexedParallelIterator, ParallelIterator};
+use rayon::slice::ParallelSliceMut;
+
+use crate::colour::Colour;
+
+const EPSILON: f64 = 1e-3;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Denoiser {
+    pub radius: usize,
+    pub patch_radius: usize,
+    pub sigma_spatial: f64,
+    pub sigma_colour: f64,
+    pub sigma_normal: f64,
+    pub sigma_albedo: f64,
+    pub sigma_depth: f64,
+}
+
+pub(crate) struct Features<'a> {
+    pub normal: &'a Rgb32FImage,
+    pub albedo: &'a Rgb32FImage,
+    pub depth: &'a Rgb32FImage,
+}
+
+impl Default for Denoiser {
+    fn default() -> Self {
+        Self {
+            radius: 6,
+            patch_radius: 1,
+            sigma_spatial: 4.0,
+            sigma_colour: 0.6,
+            sigma_normal: 0.3,
+            sigma_albedo: 0.1,
+            sigma_depth: 0.05,
+        }
+    }
+}
+
+impl Denoiser {
+    pub fn bilateral() -> Self {
+        Self {
+            patch_radius: 0,
+            sigma_colour: 1.5,
+            ..Self::default()
+        }
+    }
+
+    pub fn non_local_means() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn denoise(&self, image: &Rgb32FImage, features: &Features) -> Rgb32FImage {
+        let (width, height) = image.dimensions();
+        // Filter the irradiance rather than the radiance so texture detail survives.
+        let mut irradiance = image.clone();
+        for (pixel, albedo) in irradiance.pixels_mut().zip(features.albedo.pixels()) {
+            *pixel = demodulate(pixel, albedo);
+        }
+        let mut result = Rgb32FImage::new(width, height);
+        result
+            .par_chunks_mut(3 * width as usize)
+            .enumerate()
+            .for_each(|(y, row)| {
+                for (x, pixel) in row.chunks_mut(3).enumerate() {
+                    let c = self.filter_pixel(&irradiance, features, x as i64, y as i64);
+                    let c = c.attenuate(&modulation(features.albedo.get_pixel(x as u32, y as u32)));
+                    pixel.copy_from_slice(&c.linear().0);
+                }
+            });
+        result
+    }
+
+    fn filter_pixel(&self, image: &Rgb32FImage, features: &Features, x: i64, y: i64) -> Colour {
+        let (width, height) = (image.width() as i64, image.height() as i64);
+        let radius = self.radius as i64;
+        let normal = Colour::from(features.normal.get_pixel(x as u32, y as u32));
+        let albedo = Colour::from(features.albedo.get_pixel(x as u32, y as u32));
+        let depth = features.depth.get_pixel(x as u32, y as u32).0[0] as f64;
+
+        let mut sum = Colour::BLACK;
+        let mut weights = 0.0;
+        for qy in (y - radius).max(0)..(y + radius + 1).min(height) {
+            for qx in (x - radius).max(0)..(x + radius + 1).min(width) {
+                let spatial = ((qx - x).pow(2) + (qy - y).pow(2)) as f64;
+                let q_normal = Colour::from(features.normal.get_pixel(qx as u32, qy as u32));
+                let q_albedo = Colour::from(features.albedo.get_pixel(qx as u32, qy as u32));
+                let q_depth = features.depth.get_pixel(qx as u32, qy as u32).0[0] as f64;
+                let exponent = spatial / (2.0 * self.sigma_spatial.powi(2))
+                    + self.patch_distance(image, x, y, qx, qy) / (2.0 * self.sigma_colour.powi(2))
+                    + distance2(normal, q_normal) / (2.0 * self.sigma_normal.powi(2))
+                    + distance2(albedo, q_albedo) / (2.0 * self.sigma_albedo.powi(2))
+                    + depth_distance2(depth, q_depth) / (2.0 * self.sigma_depth.powi(2));
+                let weight = (-exponent).exp();
+                sum += weight * Colour::from(image.get_pixel(qx as u32, qy as u32));
+                weights += weight;
+            }
+        }
+        sum / weights
+    }
+
+    fn patch_distance(&self, image: &Rgb32FImage, x: i64, y: i64, qx: i64, qy: i64) -> f64 {
+        let (width, height) = (image.width() as i64, image.height() as i64);
+        let patch = self.patch_radius as i64;
+        let mut distance = 0.0;
+        let mut count = 0;
+        for dy in -patch..=patch {
+            for dx in -patch..=patch {
+                let (px, py) = (x + dx, y + dy);
+                let (rx, ry) = (qx + dx, qy + dy);
+                if px < 0 || py < 0 || rx < 0 || ry < 0 {
+                    continue;
+                }
+                if px >= width || rx >= width || py >= height || ry >= height {
+                    continue;
+                }
+                let p = Colour::from(image.get_pixel(px as u32, py as u32));
+                let r = Colour::from(image.get_pixel(rx as u32, ry as u32));
+                // Normalise by brightness so bright and dark regions are smoothed alike.
+                distance +=
+                    distance2(p, r) / (EPSILON + p.luminance().powi(2) + r.luminance().powi(2));
+                count += 1;
+            }
+        }
+        distance / count.max(1) as f64
+    }
+}
+
+fn modulation(albedo: &Rgb<f32>) -> Colour {
+    let albedo = Colour::from(albedo);
+    if albedo.luminance() < EPSILON {
+        Colour::WHITE
+    } else {
+        albedo
+    }
+}
+
+fn demodulate(pixel: &Rgb<f32>, albedo: &Rgb<f32>) -> Rgb<f32> {
+    let albedo = modulation(albedo).linear().0;
+    Rgb([
+        pixel.0[0] / albedo[0].max(EPSILON as f32),
+        pixel.0[1] / albedo[1].max(EPSILON as f32),
+        pixel.0[2] / albedo[2].max(EPSILON as f32),
+    ])
+}
+
+fn distance2(a: Colour, b: Colour) -> f64 {
+    (a - b).length_squared()
+}
+
+fn depth_distance2(a: f64, b: f64) -> f64 {
+    match (a.is_finite(), b.is_finite()) {
+        (true, true) => ((a - b) / a.abs().max(b.abs()).max(EPSILON)).powi(2),
+        (false, false) => 0.0,
+        _ => f64::INFINITY,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: u32 = 16;
+
+    fn image(f: impl Fn(u32, u32) -> [f32; 3]) -> Rgb32FImage {
+        Rgb32FImage::from_fn(SIZE, SIZE, |x, y| Rgb(f(x, y)))
+    }
+
+    fn denoise(denoiser: &Denoiser, noisy: &Rgb32FImage, normal: &Rgb32FImage) -> Rgb32FImage {
+        let albedo = image(|_, _| [1.0; 3]);
+        let depth = image(|_, _| [1.0; 3]);
+        denoiser.denoise(
+            noisy,
+            &Features {
+                normal,
+                albedo: &albedo,
+                depth: &depth,
+            },
+        )
+    }
+
+    fn noise(x: u32, y: u32) -> f32 {
+        let mut rng = fastrand::Rng::with_seed((y * SIZE + x) as u64);
+        rng.f32() - 0.5
+    }
+
+    fn variance(image: &Rgb32FImage, xs: std::ops::Range<u32>) -> f64 {
+        let values: Vec<f64> = image
+            .enumerate_pixels()
+            .filter(|(x, _, _)| xs.contains(x))
+            .map(|(_, _, p)| p.0[0] as f64)
+            .collect();
+        let mean = values.iter().sum::<f64>() / values.len() as f64;
+        values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / values.len() as f64
+    }
+
+    #[test]
+    fn constant_images_are_unchanged() {
+        let flat = image(|_, _| [0.25, 0.5, 0.75]);
+        let normal = image(|_, _| [0.0, 0.0, 1.0]);
+        for denoiser in [Denoiser::bilateral(), Denoiser::non_local_means()] {
+            let result = denoise(&denoiser, &flat, &normal);
+            for (a, b) in result.pixels().zip(flat.pixels()) {
+                for c in 0..3 {
+                    assert!((a.0[c] - b.0[c]).abs() < 1e-5);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn noise_is_reduced_without_blurring_across_feature_edges() {
+        // Two flat regions that differ in brightness and surface orientation, plus noise.
+        let noisy = image(|x, y| [if x < SIZE / 2 { 0.2 } else { 0.8 } + 0.2 * noise(x, y); 3]);
+        let normal = image(|x, _| {
+            if x < SIZE / 2 {
+                [1.0, 0.0, 0.0]
+            } else {
+                [0.0, 1.0, 0.0]
+            }
+        });
+        for denoiser in [Denoiser::bilateral(), Denoiser::non_local_means()] {
+            let result = denoise(&denoiser, &noisy, &normal);
+            for xs in [0..SIZE / 2, SIZE / 2..SIZE] {
+                assert!(variance(&result, xs.clone()) < 0.25 * variance(&noisy, xs));
+            }
+            for (x, _, p) in result.enumerate_pixels() {
+                let expected = if x < SIZE / 2 { 0.2 } else { 0.8 };
+                assert!((p.0[0] - expected).abs() < 0.1, "{denoiser:?} at x = {x}");
+            }
+        }
+    }
+
+    #[test]
+    fn texture_detail_survives_through_the_albedo() {
+        let albedo = image(|x, y| [if (x + y) % 2 == 0 { 0.9 } else { 0.1 }; 3]);
+        let textured = image(|x, y| [0.5 * albedo.get_pixel(x, y).0[0]; 3]);
+        let normal = image(|_, _| [0.0, 0.0, 1.0]);
+        let depth = image(|_, _| [1.0; 3]);
+        let result = Denoiser::default().denoise(
+            &textured,
+            &Features {
+                normal: &normal,
+                albedo: &albedo,
+                depth: &depth,
+            },
+        );
+        for (a, b) in result.pixels().zip(textured.pixels()) {
+            assert!((a.0[0] - b.0[0]).abs() < 1e-5);
+        }
+    }
+}
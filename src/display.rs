@@ -0,0 +1,275 @@
+use image::Rgb;
+
+use crate::colour::Colour;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum ToneCurve {
+    #[default]
+    Clamp,
+    Reinhard {
+        white: f64,
+    },
+    Filmic,
+    Aces,
+}
+
+impl ToneCurve {
+    pub fn reinhard(white: f64) -> Self {
+        Self::Reinhard { white }
+    }
+}
+
+// Both white balance and the Reinhard curve divide by their white point, so every use of one
+// goes through here to keep it above zero.
+const MIN_WHITE: f64 = 1e-6;
+
+fn white_point(white: f64) -> f64 {
+    white.max(MIN_WHITE)
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum TransferFunction {
+    #[default]
+    Srgb,
+    Gamma(f64),
+    Linear,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct DisplayTransform {
+    pub exposure: f64,
+    pub white: Colour,
+    pub tone_curve: ToneCurve,
+    pub transfer: TransferFunction,
+}
+
+impl Default for DisplayTransform {
+    fn default() -> Self {
+        Self {
+            exposure: 0.0,
+            white: Colour::WHITE,
+            tone_curve: ToneCurve::default(),
+            transfer: TransferFunction::default(),
+        }
+    }
+}
+
+impl DisplayTransform {
+    // Balances for a black body illuminant, so light of that temperature is shown as neutral.
+    pub fn with_temperature(mut self, kelvin: f64) -> Self {
+        let [r, g, b] = blackbody(kelvin);
+        let [r0, g0, b0] = blackbody(6504.0);
+        self.white = Colour::new(r / r0, g / g0, b / b0);
+        self
+    }
+
+    // The scene-referred adjustments, kept linear so they also apply to HDR output.
+    pub(crate) fn expose(&self, colour: Colour) -> Colour {
+        let [r, g, b] = colour.components();
+        let white = Colour::from_components(self.white.components().map(white_point));
+        let [wr, wg, wb] = white.components();
+        // Dividing by the white point would also change brightness, so keep its luminance.
+        let scale = 2f64.powf(self.exposure) * white.luminance();
+        scale * Colour::new(r / wr, g / wg, b / wb)
+    }
+
+    pub fn encode(&self, colour: Colour) -> Rgb<f32> {
+        let [r, g, b] = self.tone_map(colour).components();
+        Rgb([r, g, b].map(|c| self.transfer(c.clamp(0.0, 1.0)) as f32))
+    }
+
+    fn tone_map(&self, colour: Colour) -> Colour {
+        match self.tone_curve {
+            ToneCurve::Clamp => colour,
+            // Reinhard on luminance, which keeps the hue of bright colours.
+            ToneCurve::Reinhard { white } => {
+                let l = colour.luminance();
+                if l <= 0.0 {
+                    return Colour::BLACK;
+                }
+                let white = white_point(white);
+                let mapped = l * (1.0 + l / (white * white)) / (1.0 + l);
+                (mapped / l) * colour
+            }
+            // Hable's curve from Uncharted 2, normalised to a linear white of 11.2.
+            ToneCurve::Filmic => {
+                let curve = |x: f64| {
+                    let (a, b, c, d, e, f) = (0.15, 0.50, 0.10, 0.20, 0.02, 0.30);
+                    ((x * (a * x + c * b) + d * e) / (x * (a * x + b) + d * f)) - e / f
+                };
+                let [r, g, b] = colour.components();
+                let white = curve(11.2);
+                Colour::new(curve(2.0 * r), curve(2.0 * g), curve(2.0 * b)) / white
+            }
+            // Stephen Hill's fit of the ACES reference and output transforms.
+            ToneCurve::Aces => {
+                const INPUT: [[f64; 3]; 3] = [
+                    [0.59719, 0.35458, 0.04823],
+                    [0.07600, 0.90834, 0.01566],
+                    [0.02840, 0.13383, 0.83777],
+                ];
+                const OUTPUT: [[f64; 3]; 3] = [
+                    [1.60475, -0.53108, -0.07367],
+                    [-0.10208, 1.10813, -0.00605],
+                    [-0.00327, -0.07276, 1.07602],
+                ];
+                let fit = |v: f64| {
+                    (v * (v + 0.0245786) - 0.000090537)
+                        / (v * (0.983729 * v + 0.4329510) + 0.238081)
+                };
+                let v = multiply(&INPUT, colour.components()).map(fit);
+                Colour::from_components(multiply(&OUTPUT, v))
+            }
+        }
+    }
+
+    fn transfer(&self, c: f64) -> f64 {
+        match self.transfer {
+            TransferFunction::Srgb => {
+                if c <= 0.0031308 {
+                    12.92 * c
+                } else {
+                    1.055 * c.powf(1.0 / 2.4) - 0.055
+                }
+            }
+            TransferFunction::Gamma(gamma) => c.powf(1.0 / gamma),
+            TransferFunction::Linear => c,
+        }
+    }
+}
+
+fn multiply(m: &[[f64; 3]; 3], v: [f64; 3]) -> [f64; 3] {
+    m.map(|row| row[0] * v[0] + row[1] * v[1] + row[2] * v[2])
+}
+
+// Linear sRGB of a black body of unit luminance, from Kang et al.'s fit of the Planckian locus.
+fn blackbody(kelvin: f64) -> [f64; 3] {
+    let t = kelvin.clamp(1667.0, 25000.0);
+    let x = if t <= 4000.0 {
+        -0.2661239e9 / t.powi(3) - 0.2343589e6 / t.powi(2) + 0.8776956e3 / t + 0.179910
+    } else {
+        -3.0258469e9 / t.powi(3) + 2.1070379e6 / t.powi(2) + 0.2226347e3 / t + 0.240390
+    };
+    let y = if t <= 2222.0 {
+        -1.1063814 * x.powi(3) - 1.34811020 * x.powi(2) + 2.18555832 * x - 0.20219683
+    } else if t <= 4000.0 {
+        -0.9549476 * x.powi(3) - 1.37418593 * x.powi(2) + 2.09137015 * x - 0.16748867
+    } else {
+        3.0817580 * x.powi(3) - 5.87338670 * x.powi(2) + 3.75112997 * x - 0.37001483
+    };
+    let xyz = [x / y, 1.0, (1.0 - x - y) / y];
+    multiply(
+        &[
+            [3.2406, -1.5372, -0.4986],
+            [-0.9689, 1.8758, 0.0415],
+            [0.0557, -0.2040, 1.0570],
+        ],
+        xyz,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CURVES: [ToneCurve; 4] = [
+        ToneCurve::Clamp,
+        ToneCurve::Reinhard { white: 4.0 },
+        ToneCurve::Filmic,
+        ToneCurve::Aces,
+    ];
+
+    fn grey(c: f64) -> Colour {
+        Colour::new(c, c, c)
+    }
+
+    #[test]
+    fn exposure_scales_by_stops() {
+        let colour = Colour::new(0.25, 0.5, 1.0);
+        for (exposure, scale) in [(0.0, 1.0), (1.0, 2.0), (-2.0, 0.25)] {
+            let display = DisplayTransform {
+                exposure,
+                ..DisplayTransform::default()
+            };
+            let exposed = display.expose(colour).components();
+            for (c, expected) in exposed.into_iter().zip(colour.components()) {
+                assert!(
+                    (c - scale * expected).abs() < 1e-12,
+                    "{exposure}: {exposed:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn white_balance_keeps_its_white_neutral() {
+        let display = DisplayTransform::default().with_temperature(3200.0);
+        let [r, g, b] = display.expose(display.white).components();
+        assert!(
+            (r - g).abs() < 1e-12 && (g - b).abs() < 1e-12,
+            "{r} {g} {b}"
+        );
+        assert!((g - display.white.luminance()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn zero_white_points_stay_finite() {
+        let whites = [Colour::new(1.0, 0.0, 1.0), Colour::BLACK];
+        for white in whites {
+            for tone_curve in [ToneCurve::reinhard(0.0), ToneCurve::Aces] {
+                let display = DisplayTransform {
+                    white,
+                    tone_curve,
+                    ..DisplayTransform::default()
+                };
+                for colour in [Colour::BLACK, grey(0.5), Colour::new(2.0, 1.0, 0.0)] {
+                    let exposed = display.expose(colour);
+                    assert!(exposed.components().iter().all(|c| c.is_finite()));
+                    let encoded = display.encode(exposed);
+                    assert!(encoded.0.iter().all(|c| (0.0..=1.0).contains(c)));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn tone_curves_rise_from_black_and_stay_in_range() {
+        for tone_curve in CURVES {
+            let display = DisplayTransform {
+                tone_curve,
+                transfer: TransferFunction::Linear,
+                ..DisplayTransform::default()
+            };
+            assert!(display.encode(Colour::BLACK).0[1] < 1e-3, "{tone_curve:?}");
+            let mut previous = 0.0;
+            for k in 1..=100 {
+                let mapped = display.encode(grey(k as f64 * 0.2)).0[1];
+                assert!(mapped >= previous && mapped <= 1.0, "{tone_curve:?} at {k}");
+                previous = mapped;
+            }
+        }
+    }
+
+    #[test]
+    fn tone_curves_map_their_white_to_one() {
+        let reinhard = DisplayTransform {
+            tone_curve: ToneCurve::reinhard(4.0),
+            ..DisplayTransform::default()
+        };
+        assert!((reinhard.tone_map(grey(4.0)).components()[0] - 1.0).abs() < 1e-12);
+        let filmic = DisplayTransform {
+            tone_curve: ToneCurve::Filmic,
+            ..DisplayTransform::default()
+        };
+        assert!((filmic.tone_map(grey(5.6)).components()[0] - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn srgb_encodes_the_standard_curve() {
+        let display = DisplayTransform::default();
+        assert_eq!(display.encode(Colour::BLACK).0, [0.0; 3]);
+        assert!((display.encode(Colour::WHITE).0[0] - 1.0).abs() < 1e-6);
+        assert!((display.encode(grey(0.5)).0[0] - 0.735357).abs() < 1e-5);
+        assert!((display.encode(grey(0.002)).0[0] - 0.02584).abs() < 1e-5);
+    }
+}
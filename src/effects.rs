@@ -15,6 +15,12 @@ pub trait RenderFilter {
 
 pub(crate) struct TrivialFilter;
 
+impl<F: RenderFilter + ?Sized> RenderFilter for &F {
+    fn filter(&self, renderer: &Renderer, objects: &mut Vec<Arc<dyn Hittable>>) {
+        (**self).filter(renderer, objects)
+    }
+}
+
 impl RenderFilter for TrivialFilter {
     fn filter(&self, _renderer: &Renderer, _objects: &mut Vec<Arc<dyn Hittable>>) {}
 }
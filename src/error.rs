@@ -3,10 +3,21 @@ use std::error::Error;
 use image::ImageError;
 
 use crate::objects::WavefrontObjError;
+use crate::render::Frame;
 
 pub enum RenderError {
     ImageError(ImageError),
     ObjectConstruction(WavefrontObjError),
+    IOError(std::io::Error),
+    InvalidCheckpoint(String),
+    CheckpointMismatch(String),
+    EmptyCrop,
+    InvalidAdaptiveSampling {
+        min_samples: usize,
+        max_samples: usize,
+    },
+    // Carries what was traced before the render stopped.
+    Cancelled(Box<Frame>),
 }
 
 impl std::fmt::Display for RenderError {
@@ -14,6 +25,23 @@ impl std::fmt::Display for RenderError {
         match *self {
             Self::ImageError(ref err) => write!(f, "{err}"),
             Self::ObjectConstruction(ref err) => write!(f, "{err}"),
+            Self::IOError(ref err) => write!(f, "{err}"),
+            Self::InvalidCheckpoint(ref file) => write!(f, "{file}: Not a valid checkpoint"),
+            Self::CheckpointMismatch(ref file) => {
+                write!(
+                    f,
+                    "{file}: Checkpoint was written for a different scene or camera"
+                )
+            }
+            Self::EmptyCrop => write!(f, "Crop window does not overlap the image"),
+            Self::InvalidAdaptiveSampling {
+                min_samples,
+                max_samples,
+            } => write!(
+                f,
+                "Adaptive sampling needs 0 < min_samples <= max_samples, got {min_samples} and {max_samples}"
+            ),
+            Self::Cancelled(_) => write!(f, "Rendering was cancelled"),
         }
     }
 }
@@ -29,6 +57,8 @@ impl Error for RenderError {
         match *self {
             Self::ImageError(ref err) => Some(err),
             Self::ObjectConstruction(ref err) => Some(err),
+            Self::IOError(ref err) => Some(err),
+            _ => None,
         }
     }
 }
@@ -44,3 +74,9 @@ impl From<WavefrontObjError> for RenderError {
         Self::ObjectConstruction(value)
     }
 }
+
+impl From<std::io::Error> for RenderError {
+    fn from(value: std::io::Error) -> Self {
+        Self::IOError(value)
+    }
+}
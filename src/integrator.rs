@@ -0,0 +1,362 @@
+use std::fmt::Debug;
+
+// Everything an integrator outside the crate needs to implement the trait.
+pub use crate::{
+    aov::SurfaceInfo,
+    objects::{Hittable, Interval},
+    ray::Ray,
+    sampler::SampleStream,
+    scene::Scene,
+    texture::Textured,
+};
+use crate::{
+    colour::Colour,
+    linalg::Vec3,
+    material::ScatterResult,
+    objects::sphere_uv,
+    random::{CosinePDF, DirectionalPDF, HittablePDF},
+    statistics::{self, Counter},
+};
+
+// Light arriving along a camera ray, split by the bounce it was emitted at for the lighting AOVs.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Radiance {
+    pub emitted: Colour,
+    pub direct: Colour,
+    pub indirect: Colour,
+}
+
+impl Radiance {
+    pub fn add(&mut self, bounce: usize, colour: Colour) {
+        match bounce {
+            0 => self.emitted += colour,
+            1 => self.direct += colour,
+            _ => self.indirect += colour,
+        }
+    }
+
+    pub fn total(&self) -> Colour {
+        self.emitted + self.direct + self.indirect
+    }
+}
+
+pub struct TraceContext<'a> {
+    pub world: &'a dyn Hittable,
+    pub lights: &'a Scene,
+    pub background: &'a dyn Textured,
+    pub max_depth: usize,
+}
+
+impl TraceContext<'_> {
+    pub fn sky(&self, ray: &Ray) -> Colour {
+        let (u, v) = sphere_uv(ray.direction.normalize());
+        self.background.value(u, v, Vec3::ZERO)
+    }
+
+    // Whatever is seen first along a shadow ray: the emission of the surface it hits, or the sky.
+    pub fn emission_along(&self, ray: Ray) -> Colour {
+        statistics::add(Counter::ShadowRays, 1);
+        match self.world.hit(&ray, Interval::new(0.001, f64::INFINITY)) {
+            Some(rec) => rec.material.emit(&rec, rec.u, rec.v, rec.p),
+            None => self.sky(&ray),
+        }
+    }
+}
+
+pub trait Integrator: Debug + Send + Sync {
+    // The surface seen by the camera ray is reported for the geometric AOVs and the denoiser.
+    fn radiance(
+        &self,
+        ray: Ray,
+        scene: &TraceContext,
+        samples: &mut SampleStream,
+        surface: &mut Option<SurfaceInfo>,
+    ) -> Radiance;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RussianRoulette {
+    pub min_depth: usize,
+}
+
+impl Default for RussianRoulette {
+    fn default() -> Self {
+        Self { min_depth: 3 }
+    }
+}
+
+// Unidirectional path tracing with next event estimation, weighted against BSDF sampling with
+// multiple importance sampling.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PathTracer {
+    pub roulette: RussianRoulette,
+}
+
+impl Integrator for PathTracer {
+    fn radiance(
+        &self,
+        mut ray: Ray,
+        scene: &TraceContext,
+        samples: &mut SampleStream,
+        surface: &mut Option<SurfaceInfo>,
+    ) -> Radiance {
+        let has_lights = !scene.lights.objects().is_empty();
+        let mut radiance = Radiance::default();
+        let mut throughput = Colour::WHITE;
+        // The MIS weight for emission found by the current ray, which is one unless it was
+        // sampled from a BSDF that next event estimation could also have found the light from.
+        let mut emission_weight = 1.0;
+        for depth in 0..scene.max_depth {
+            // Russian roulette: past the minimum depth, paths carrying little light are ended at
+            // random and the survivors are boosted to make up for the ones that were not.
+            if depth > 0 && depth >= self.roulette.min_depth {
+                let survival = throughput.max_component().min(1.0);
+                if survival < 1.0 && samples.next_1d() >= survival {
+                    statistics::add(Counter::RoulettePaths, 1);
+                    return radiance;
+                }
+                throughput = throughput / survival;
+            }
+            ray = ray.with_sample(samples.next_1d());
+            statistics::add(Counter::Rays, 1);
+            let Some(rec) = scene.world.hit(&ray, Interval::new(0.001, f64::INFINITY)) else {
+                let sky = scene.sky(&ray);
+                radiance.add(depth, emission_weight * sky.attenuate(&throughput));
+                return radiance;
+            };
+            let emitted = rec.material.emit(&rec, rec.u, rec.v, rec.p);
+            radiance.add(depth, emission_weight * emitted.attenuate(&throughput));
+            let scatter = rec.material.scatter(ray, &rec, samples);
+            if depth == 0 {
+                *surface = Some(SurfaceInfo {
+                    p: rec.p,
+                    normal: rec.normal,
+                    albedo: scatter.as_ref().map_or(Colour::BLACK, |s| s.attenuation),
+                    object_id: rec.object_id,
+                    material_id: rec.material_id,
+                });
+            }
+            let Some(scatter) = scatter else {
+                return radiance;
+            };
+            match scatter.scattered {
+                ScatterResult::SpecularRay(specular_ray) => {
+                    throughput = throughput.attenuate(&scatter.attenuation);
+                    emission_weight = 1.0;
+                    ray = specular_ray;
+                }
+                ScatterResult::PDF(pdf) => {
+                    let light_pdf = HittablePDF::new(scene.lights, rec.p, ray.time);
+                    // Next event estimation: a shadow ray towards a light, weighted against the
+                    // chance of the BSDF sample finding the same light. At the last vertex the
+                    // BSDF sample is never traced, so the shadow ray has to carry all of it.
+                    let last = depth + 1 == scene.max_depth;
+                    if has_lights {
+                        let shadow =
+                            Ray::time_dependent(rec.p, light_pdf.generate(samples), ray.time)
+                                .with_sample(samples.next_1d());
+                        let light_value = light_pdf.value(&shadow.direction);
+                        let scattering_pdf = rec.material.scattering_pdf(ray, &rec, shadow);
+                        if light_value > 0.0 && scattering_pdf > 0.0 {
+                            let mis = if last {
+                                1.0
+                            } else {
+                                power_heuristic(light_value, pdf.value(&shadow.direction))
+                            };
+                            let weight = mis * scattering_pdf / light_value;
+                            let light = scene.emission_along(shadow);
+                            radiance.add(
+                                depth + 1,
+                                weight
+                                    * light.attenuate(&scatter.attenuation).attenuate(&throughput),
+                            );
+                        }
+                    }
+                    if last {
+                        break;
+                    }
+                    let scattered = Ray::time_dependent(rec.p, pdf.generate(samples), ray.time);
+                    let pdf_value = pdf.value(&scattered.direction);
+                    if pdf_value <= 0.0 {
+                        return radiance;
+                    }
+                    let scattering_pdf = rec.material.scattering_pdf(ray, &rec, scattered);
+                    emission_weight = if has_lights {
+                        power_heuristic(pdf_value, light_pdf.value(&scattered.direction))
+                    } else {
+                        1.0
+                    };
+                    throughput =
+                        throughput.attenuate(&(scattering_pdf / pdf_value * scatter.attenuation));
+                    ray = scattered;
+                }
+            }
+        }
+        statistics::add(Counter::MaxDepthPaths, 1);
+        radiance
+    }
+}
+
+// The fraction of the hemisphere above the first surface that is open within `distance`, shaded
+// as if lit by a uniform white sky.
+#[derive(Debug, Clone, Copy)]
+pub struct AmbientOcclusion {
+    pub distance: f64,
+}
+
+impl Default for AmbientOcclusion {
+    fn default() -> Self {
+        Self {
+            distance: f64::INFINITY,
+        }
+    }
+}
+
+impl Integrator for AmbientOcclusion {
+    fn radiance(
+        &self,
+        ray: Ray,
+        scene: &TraceContext,
+        samples: &mut SampleStream,
+        surface: &mut Option<SurfaceInfo>,
+    ) -> Radiance {
+        let mut radiance = Radiance::default();
+        let ray = ray.with_sample(samples.next_1d());
+        statistics::add(Counter::Rays, 1);
+        let Some(rec) = scene.world.hit(&ray, Interval::new(0.001, f64::INFINITY)) else {
+            return radiance;
+        };
+        *surface = Some(SurfaceInfo {
+            p: rec.p,
+            normal: rec.normal,
+            albedo: Colour::WHITE,
+            object_id: rec.object_id,
+            material_id: rec.material_id,
+        });
+        // Cosine-weighted directions cancel the cosine term, leaving just the visibility.
+        let direction = CosinePDF::new(&rec.normal).generate(samples);
+        let occlusion =
+            Ray::time_dependent(rec.p, direction, ray.time).with_sample(samples.next_1d());
+        statistics::add(Counter::ShadowRays, 1);
+        if scene
+            .world
+            .hit(&occlusion, Interval::new(0.001, self.distance))
+            .is_none()
+        {
+            radiance.add(1, Colour::WHITE);
+        }
+        radiance
+    }
+}
+
+// Veach's power heuristic with an exponent of two, for one sample from each strategy.
+fn power_heuristic(f: f64, g: f64) -> f64 {
+    let (f2, g2) = (f * f, g * g);
+    if f2 + g2 > 0.0 { f2 / (f2 + g2) } else { 0.0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::linalg::Point3;
+    use crate::material::{DiffuseLight, Lambertian};
+    use crate::objects::{Quad, Sphere};
+    use crate::progress::ProgressEvent;
+    use crate::render::Camera;
+    use crate::texture::SolidColour;
+
+    #[test]
+    fn power_heuristic_weights_sum_to_one() {
+        assert_eq!(power_heuristic(1.0, 1.0), 0.5);
+        assert_eq!(power_heuristic(3.0, 0.0), 1.0);
+        assert_eq!(power_heuristic(0.0, 3.0), 0.0);
+        assert_eq!(power_heuristic(0.0, 0.0), 0.0);
+        assert_eq!(power_heuristic(1.0, 2.0), 0.2);
+        for (f, g) in [(0.1, 7.0), (2.5, 2.4), (1e-3, 1e3)] {
+            assert!((power_heuristic(f, g) + power_heuristic(g, f) - 1.0).abs() < 1e-12);
+        }
+    }
+
+    // A grey sphere filling the view under a white sky: every path that leaves the convex
+    // sphere sees the sky, so each pixel converges to the albedo.
+    fn furnace(roulette: RussianRoulette) -> (f64, u64) {
+        let mut world = Scene::new();
+        world.add(Sphere::new(
+            Point3::ZERO,
+            1.0,
+            Lambertian::new(Colour::new(0.5, 0.5, 0.5)),
+        ));
+        let cam = Camera {
+            image_width: 8,
+            aspect_ratio: 1.0,
+            vfov: 10.0,
+            lookfrom: Point3::new(0.0, 0.0, -3.0),
+            lookat: Point3::ZERO,
+            background: SolidColour::new(Colour::WHITE),
+            ..Camera::default()
+        };
+        let frame = cam
+            .renderer(256, 10)
+            .with_progress(|_: &ProgressEvent| {})
+            .with_integrator(PathTracer { roulette })
+            .render_frame(&mut world)
+            .unwrap();
+        let mean = frame.image.pixels().map(|p| p.0[0] as f64).sum::<f64>() / 64.0;
+        (mean, frame.statistics.roulette_paths)
+    }
+
+    #[test]
+    fn russian_roulette_keeps_the_estimate_unbiased() {
+        let (exact, ended) = furnace(RussianRoulette {
+            min_depth: usize::MAX,
+        });
+        assert_eq!(ended, 0);
+        assert!((exact - 0.5).abs() < 1e-4, "{exact}");
+        // Survivors are weighted by one over the survival chance, here the albedo itself.
+        let (estimate, ended) = furnace(RussianRoulette { min_depth: 1 });
+        assert!(ended > 0);
+        assert!((estimate - 0.5).abs() < 0.02, "{estimate}");
+    }
+
+    // A grey floor under a large light with nothing else around, so all of its light is direct
+    // and a single bounce already gathers everything.
+    fn floor_under_light(max_depth: usize) -> f64 {
+        let mut world = Scene::new();
+        world.add(Quad::new(
+            Point3::new(-50.0, 0.0, -50.0),
+            Vec3::new(0.0, 0.0, 100.0),
+            Vec3::new(100.0, 0.0, 0.0),
+            Lambertian::new(Colour::new(0.5, 0.5, 0.5)),
+        ));
+        world.add(Quad::new(
+            Point3::new(-10.0, 2.0, -10.0),
+            Vec3::new(20.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 20.0),
+            DiffuseLight::from_colour(Colour::WHITE),
+        ));
+        let cam = Camera {
+            image_width: 8,
+            aspect_ratio: 1.0,
+            vfov: 20.0,
+            lookfrom: Point3::new(0.0, 1.0, 0.0),
+            lookat: Point3::ZERO,
+            up: Vec3::EZ,
+            background: SolidColour::new(Colour::BLACK),
+            ..Camera::default()
+        };
+        let frame = cam
+            .renderer(1024, max_depth)
+            .with_progress(|_: &ProgressEvent| {})
+            .render_frame(&mut world)
+            .unwrap();
+        frame.image.pixels().map(|p| p.0[0] as f64).sum::<f64>() / 64.0
+    }
+
+    #[test]
+    fn direct_light_survives_the_last_bounce() {
+        let single = floor_under_light(1);
+        let double = floor_under_light(2);
+        assert!(single > 0.3, "{single}");
+        assert!((single - double).abs() < 0.02 * double, "{single} {double}");
+    }
+}
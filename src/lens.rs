@@ -0,0 +1,116 @@
+use std::f64::consts::PI;
+use std::fmt::Debug;
+use std::path::Path;
+use std::sync::Arc;
+
+use image::GrayImage;
+
+use crate::checkpoint::Fingerprint;
+use crate::error::RenderError;
+use crate::linalg::Vec3;
+use crate::random::concentric_disk;
+
+#[derive(Debug, Default, Clone)]
+pub enum Aperture {
+    #[default]
+    Circular,
+    Polygon {
+        blades: usize,
+        rotation: f64,
+    },
+    Mask(Arc<ApertureMask>),
+}
+
+impl Aperture {
+    // A point on the aperture, within the unit square centred on the optical axis.
+    pub(crate) fn sample(&self, (u, v): (f64, f64)) -> Vec3 {
+        match self {
+            Aperture::Circular => concentric_disk((u, v)),
+            Aperture::Polygon { blades, rotation } => {
+                let blades = (*blades).max(3);
+                let scaled = u * blades as f64;
+                let k = (scaled as usize).min(blades - 1);
+                let corner = |k: usize| {
+                    let phi = rotation.to_radians() + 2.0 * PI * k as f64 / blades as f64;
+                    Vec3::new(phi.cos(), phi.sin(), 0.0)
+                };
+                let (a, b) = (corner(k), corner(k + 1));
+                // Uniform point in the triangle spanned by the centre and one edge, reusing what
+                // is left of the first coordinate after choosing the blade.
+                let r = (scaled - k as f64).sqrt();
+                r * ((1.0 - v) * a + v * b)
+            }
+            Aperture::Mask(mask) => mask.sample((u, v)),
+        }
+    }
+}
+
+pub struct ApertureMask {
+    width: usize,
+    height: usize,
+    cdf: Vec<f64>,
+    digest: u64,
+}
+
+impl ApertureMask {
+    pub fn new(image: &GrayImage) -> Self {
+        let mut total = 0.0;
+        let cdf = image
+            .pixels()
+            .map(|p| {
+                total += p.0[0] as f64;
+                total
+            })
+            .collect::<Vec<_>>();
+        // Masks of the same size and weight can still differ, so checkpoints compare the content.
+        let mut digest = Fingerprint::default();
+        for &c in &cdf {
+            digest.f64(c);
+        }
+        Self {
+            width: image.width() as usize,
+            height: image.height() as usize,
+            cdf,
+            digest: digest.finish(),
+        }
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, RenderError> {
+        Ok(Self::new(&image::open(path)?.to_luma8()))
+    }
+
+    pub(crate) fn digest(&self) -> u64 {
+        self.digest
+    }
+
+    fn sample(&self, (u, v): (f64, f64)) -> Vec3 {
+        let total = self.cdf.last().copied().unwrap_or_default();
+        if total <= 0.0 {
+            return Vec3::ZERO;
+        }
+        let target = u * total;
+        let index = self.cdf.partition_point(|&c| c <= target);
+        let index = index.min(self.cdf.len() - 1);
+        // Jitter within the texel, taking the horizontal offset from where u fell in its bin.
+        let low = if index > 0 { self.cdf[index - 1] } else { 0.0 };
+        let jitter = ((target - low) / (self.cdf[index] - low)).clamp(0.0, 1.0);
+        let x = (index % self.width) as f64 + jitter;
+        let y = (index / self.width) as f64 + v;
+        Vec3::new(
+            2.0 * x / self.width as f64 - 1.0,
+            1.0 - 2.0 * y / self.height as f64,
+            0.0,
+        )
+    }
+}
+
+impl Debug for ApertureMask {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ApertureMask")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("weight", &self.cdf.last())
+            .field("digest", &self.digest)
+            .finish()
+    }
+}
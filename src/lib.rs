@@ -1,17 +1,33 @@
 #![allow(clippy::new_ret_no_self)]
 
+pub mod adaptive;
+pub mod animation;
+pub mod aov;
 mod bounding_box;
+pub mod cancel;
+pub mod checkpoint;
 pub mod colour;
+pub mod denoise;
+pub mod display;
 pub mod effects;
 mod error;
+pub mod integrator;
+pub mod lens;
 pub mod linalg;
 pub mod material;
 pub mod objects;
+mod output;
+pub mod postprocess;
 pub mod prelude;
+pub mod progress;
 mod random;
 mod ray;
+pub mod reconstruction;
 pub mod render;
+pub mod sampler;
 mod scene;
+pub mod statistics;
+pub mod stereo;
 pub mod texture;
 pub mod transform;
 pub mod volumetrics;
@@ -59,11 +59,11 @@ impl Vec3 {
         self.x.abs() < EPSILON && self.y.abs() < EPSILON && self.z.abs() < EPSILON
     }
 
-    pub fn random(min: f64, max: f64) -> Self {
+    pub fn random(rng: &mut fastrand::Rng, min: f64, max: f64) -> Self {
         Self {
-            x: fastrand::f64() * (max - min) + min,
-            y: fastrand::f64() * (max - min) + min,
-            z: fastrand::f64() * (max - min) + min,
+            x: rng.f64() * (max - min) + min,
+            y: rng.f64() * (max - min) + min,
+            z: rng.f64() * (max - min) + min,
         }
     }
 }
@@ -5,8 +5,9 @@ use crate::{
     linalg::{Point3, Vec3},
     material::{Material, Scatter, ScatterRecord, ScatterResult},
     objects::HitRecord,
-    random::{CosinePDF, UniformSphericalPDF, random_unit_vector},
+    random::{CosinePDF, UniformSphericalPDF, uniform_sphere},
     ray::Ray,
+    sampler::SampleStream,
     texture::{SolidColour, Texture},
 };
 
@@ -28,7 +29,12 @@ impl Lambertian {
 }
 
 impl Scatter for Lambertian {
-    fn scatter(&self, _ray: Ray, hit: &HitRecord) -> Option<ScatterRecord> {
+    fn scatter(
+        &self,
+        _ray: Ray,
+        hit: &HitRecord,
+        _samples: &mut SampleStream,
+    ) -> Option<ScatterRecord> {
         Some(ScatterRecord {
             attenuation: self.texture.value(hit.u, hit.v, hit.p),
             scattered: ScatterResult::PDF(Box::new(CosinePDF::new(&hit.normal))),
@@ -62,9 +68,15 @@ impl Metal {
 }
 
 impl Scatter for Metal {
-    fn scatter(&self, ray: Ray, hit: &HitRecord) -> Option<ScatterRecord> {
+    fn scatter(
+        &self,
+        ray: Ray,
+        hit: &HitRecord,
+        samples: &mut SampleStream,
+    ) -> Option<ScatterRecord> {
         let scatter_direction = ray.direction - 2.0 * ray.direction.dot(&hit.normal) * hit.normal;
-        let scatter_direction = scatter_direction.normalize() + self.fuzz * random_unit_vector();
+        let scatter_direction =
+            scatter_direction.normalize() + self.fuzz * uniform_sphere(samples.next_2d());
         let scattered = Ray::time_dependent(hit.p, scatter_direction, ray.time);
         Some(ScatterRecord {
             attenuation: self.albedo,
@@ -96,7 +108,12 @@ impl Dielectric {
 }
 
 impl Scatter for Dielectric {
-    fn scatter(&self, ray: Ray, hit: &HitRecord) -> Option<ScatterRecord> {
+    fn scatter(
+        &self,
+        ray: Ray,
+        hit: &HitRecord,
+        samples: &mut SampleStream,
+    ) -> Option<ScatterRecord> {
         let attenuation = Colour::WHITE;
         let ri = if hit.front_face {
             1.0 / self.refraction_index
@@ -107,7 +124,7 @@ impl Scatter for Dielectric {
         let cos_theta = (-uv).dot(&hit.normal).min(1.0);
         let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
         let cannot_refract = ri * sin_theta > 1.0;
-        let dir = if cannot_refract || reflectance(cos_theta, ri) > fastrand::f64() {
+        let dir = if cannot_refract || reflectance(cos_theta, ri) > samples.next_1d() {
             uv - 2.0 * uv.dot(&hit.normal) * hit.normal
         } else {
             refract(uv, hit.normal, ri)
@@ -148,7 +165,12 @@ impl DiffuseLight {
 }
 
 impl Scatter for DiffuseLight {
-    fn scatter(&self, _ray: Ray, _hit: &HitRecord) -> Option<ScatterRecord> {
+    fn scatter(
+        &self,
+        _ray: Ray,
+        _hit: &HitRecord,
+        _samples: &mut SampleStream,
+    ) -> Option<ScatterRecord> {
         None
     }
 
@@ -194,7 +216,12 @@ impl Isotropic {
 }
 
 impl Scatter for Isotropic {
-    fn scatter(&self, _ray: Ray, hit: &HitRecord) -> Option<ScatterRecord> {
+    fn scatter(
+        &self,
+        _ray: Ray,
+        hit: &HitRecord,
+        _samples: &mut SampleStream,
+    ) -> Option<ScatterRecord> {
         Some(ScatterRecord {
             attenuation: self.texture.value(hit.u, hit.v, hit.p),
             scattered: ScatterResult::PDF(Box::new(UniformSphericalPDF)),
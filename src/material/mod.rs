@@ -7,7 +7,10 @@ use std::{
     sync::Arc,
 };
 
-use crate::{colour::Colour, linalg::Point3, objects::HitRecord, random::DirectionalPDF, ray::Ray};
+use crate::{
+    colour::Colour, linalg::Point3, objects::HitRecord, random::DirectionalPDF, ray::Ray,
+    sampler::SampleStream,
+};
 
 pub struct ScatterRecord {
     pub attenuation: Colour,
@@ -20,7 +23,12 @@ pub enum ScatterResult {
 }
 
 pub trait Scatter: std::fmt::Debug + Send + Sync {
-    fn scatter(&self, ray: Ray, hit: &HitRecord) -> Option<ScatterRecord>;
+    fn scatter(
+        &self,
+        ray: Ray,
+        hit: &HitRecord,
+        samples: &mut SampleStream,
+    ) -> Option<ScatterRecord>;
     fn emit(&self, hit: &HitRecord, u: f64, v: f64, p: Point3) -> Colour;
     fn scattering_pdf(&self, ray: Ray, hit: &HitRecord, scattered: Ray) -> f64;
     fn is_emissive(&self) -> bool;
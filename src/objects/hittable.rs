@@ -4,9 +4,10 @@ use std::ops::Add;
 use crate::{
     bounding_box::AaBb,
     linalg::{Point3, Vec3},
-    material::Scatter,
+    material::{Material, Scatter},
     objects::Collection,
     ray::Ray,
+    sampler::SampleStream,
 };
 
 #[derive(Debug)]
@@ -18,6 +19,8 @@ pub struct HitRecord<'a> {
     pub u: f64,
     pub v: f64,
     pub front_face: bool,
+    pub object_id: usize,
+    pub material_id: usize,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -87,9 +90,10 @@ impl Add<f64> for Interval {
 }
 
 pub trait Hittable: Debug + Send + Sync {
-    fn hit(&self, ray: &Ray, range: Interval) -> Option<HitRecord>;
+    fn hit(&self, ray: &Ray, range: Interval) -> Option<HitRecord<'_>>;
     fn bbox(&self) -> AaBb;
-    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64;
-    fn random(&self, origin: &Point3) -> Vec3;
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, time: f64) -> f64;
+    fn random(&self, origin: &Point3, time: f64, samples: &mut SampleStream) -> Vec3;
     fn lights(&self) -> Collection;
+    fn materials(&self) -> Vec<Material>;
 }
@@ -6,6 +6,7 @@ use crate::{
     material::Material,
     objects::{Collection, HitRecord, Hittable, Interval, Object},
     ray::Ray,
+    sampler::SampleStream,
 };
 
 const EPSILON: f64 = 1e-8;
@@ -53,7 +54,7 @@ impl Quad {
 }
 
 impl Hittable for Quad {
-    fn hit(&self, ray: &Ray, range: Interval) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, range: Interval) -> Option<HitRecord<'_>> {
         let det = self.normal.dot(&ray.direction);
         // No hit if the ray is parallel to the plane.
         if det.abs() < EPSILON {
@@ -82,6 +83,8 @@ impl Hittable for Quad {
             material: self.material.as_ref(),
             normal,
             front_face,
+            object_id: 0,
+            material_id: 0,
             u: alpha,
             v: beta,
         })
@@ -91,9 +94,9 @@ impl Hittable for Quad {
         self.bbox
     }
 
-    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, time: f64) -> f64 {
         if let Some(rec) = self.hit(
-            &Ray::new(*origin, *direction),
+            &Ray::time_dependent(*origin, *direction, time),
             Interval::new(0.001, f64::INFINITY),
         ) {
             let distance_squared = rec.t * rec.t * direction.dot(direction);
@@ -103,8 +106,9 @@ impl Hittable for Quad {
             0.0
         }
     }
-    fn random(&self, origin: &Point3) -> Vec3 {
-        let p = self.q + (fastrand::f64() * self.u) + (fastrand::f64() * self.v);
+    fn random(&self, origin: &Point3, _time: f64, samples: &mut SampleStream) -> Vec3 {
+        let (alpha, beta) = samples.next_2d();
+        let p = self.q + (alpha * self.u) + (beta * self.v);
         p - *origin
     }
     fn lights(&self) -> Collection {
@@ -114,4 +118,7 @@ impl Hittable for Quad {
         }
         res
     }
+    fn materials(&self) -> Vec<Material> {
+        vec![self.material.clone()]
+    }
 }
@@ -8,6 +8,7 @@ use crate::{
     material::Material,
     objects::{Collection, Object},
     ray::Ray,
+    sampler::SampleStream,
 };
 
 #[derive(Clone, Debug)]
@@ -32,7 +33,7 @@ impl Sphere {
 }
 
 impl Hittable for Sphere {
-    fn hit(&self, ray: &Ray, range: Interval) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, range: Interval) -> Option<HitRecord<'_>> {
         let oc = self.center - ray.origin;
         let a = ray.direction.dot(&ray.direction);
         let h = ray.direction.dot(&oc);
@@ -64,16 +65,18 @@ impl Hittable for Sphere {
             u,
             v,
             front_face,
+            object_id: 0,
+            material_id: 0,
             material: self.material.as_ref(),
         })
     }
     fn bbox(&self) -> AaBb {
         self.bbox
     }
-    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, time: f64) -> f64 {
         if self
             .hit(
-                &Ray::new(*origin, *direction),
+                &Ray::time_dependent(*origin, *direction, time),
                 Interval::new(0.001, f64::INFINITY),
             )
             .is_some()
@@ -87,11 +90,15 @@ impl Hittable for Sphere {
             0.0
         }
     }
-    fn random(&self, origin: &Point3) -> Vec3 {
+    fn random(&self, origin: &Point3, _time: f64, samples: &mut SampleStream) -> Vec3 {
         let direction = self.center - *origin;
         let distance_squared = direction.dot(&direction);
         let uvw = ONB::from_normal(&direction);
-        uvw.transform(&random_to_sphere(self.radius, distance_squared))
+        uvw.transform(&random_to_sphere(
+            self.radius,
+            distance_squared,
+            samples.next_2d(),
+        ))
     }
     fn lights(&self) -> Collection {
         let mut res = Collection::new();
@@ -100,11 +107,16 @@ impl Hittable for Sphere {
         }
         res
     }
+    fn materials(&self) -> Vec<Material> {
+        vec![self.material.clone()]
+    }
 }
 
 #[derive(Clone, Debug)]
 pub struct MovingSphere {
     center: Ray,
+    start: f64,
+    end: f64,
     radius: f64,
     material: Material,
     bbox: AaBb,
@@ -112,6 +124,17 @@ pub struct MovingSphere {
 
 impl MovingSphere {
     pub fn new(center1: Point3, center2: Point3, radius: f64, material: Material) -> Object {
+        Self::between(center1, 0.0, center2, 1.0, radius, material)
+    }
+
+    pub fn between(
+        center1: Point3,
+        time1: f64,
+        center2: Point3,
+        time2: f64,
+        radius: f64,
+        material: Material,
+    ) -> Object {
         let center = Ray::new(center1, center2 - center1);
         let rvec = Vec3::new(radius, radius, radius);
         let box1 = AaBb::new(center1 - rvec, center1 + rvec);
@@ -119,16 +142,28 @@ impl MovingSphere {
         let bbox = AaBb::enclosing(&box1, &box2);
         Object::new(Arc::new(Self {
             center,
+            start: time1,
+            end: time2,
             radius,
             material,
             bbox,
         }))
     }
+
+    // The sphere rests at its end points outside of the motion interval.
+    fn center_at(&self, time: f64) -> Point3 {
+        let t = (time - self.start) / (self.end - self.start);
+        self.center.at(if t.is_finite() {
+            t.clamp(0.0, 1.0)
+        } else {
+            0.0
+        })
+    }
 }
 
 impl Hittable for MovingSphere {
-    fn hit(&self, ray: &Ray, range: Interval) -> Option<HitRecord> {
-        let oc = self.center.at(ray.time) - ray.origin;
+    fn hit(&self, ray: &Ray, range: Interval) -> Option<HitRecord<'_>> {
+        let oc = self.center_at(ray.time) - ray.origin;
         let a = ray.direction.dot(&ray.direction);
         let h = ray.direction.dot(&oc);
         let c = oc.dot(&oc) - self.radius * self.radius;
@@ -148,7 +183,7 @@ impl Hittable for MovingSphere {
             }
         }
         let p = ray.at(t);
-        let normal = (p - self.center.at(ray.time)) / self.radius;
+        let normal = (p - self.center_at(ray.time)) / self.radius;
         let front_face = ray.direction.dot(&normal) < 0.0;
         let normal = if front_face { normal } else { -normal };
         let (u, v) = sphere_uv(normal);
@@ -159,16 +194,18 @@ impl Hittable for MovingSphere {
             u,
             v,
             front_face,
+            object_id: 0,
+            material_id: 0,
             material: self.material.as_ref(),
         })
     }
     fn bbox(&self) -> AaBb {
         self.bbox
     }
-    fn pdf_value(&self, _origin: &Point3, _direction: &Vec3) -> f64 {
+    fn pdf_value(&self, _origin: &Point3, _direction: &Vec3, _time: f64) -> f64 {
         todo!()
     }
-    fn random(&self, _origin: &Point3) -> Vec3 {
+    fn random(&self, _origin: &Point3, _time: f64, _samples: &mut SampleStream) -> Vec3 {
         todo!()
     }
     fn lights(&self) -> Collection {
@@ -178,6 +215,9 @@ impl Hittable for MovingSphere {
         }
         res
     }
+    fn materials(&self) -> Vec<Material> {
+        vec![self.material.clone()]
+    }
 }
 
 pub(crate) fn sphere_uv(p: Point3) -> (f64, f64) {
@@ -188,9 +228,7 @@ pub(crate) fn sphere_uv(p: Point3) -> (f64, f64) {
     (phi / (2.0 * PI), theta / PI)
 }
 
-fn random_to_sphere(radius: f64, distance_squared: f64) -> Vec3 {
-    let r1 = fastrand::f64();
-    let r2 = fastrand::f64();
+fn random_to_sphere(radius: f64, distance_squared: f64, (r1, r2): (f64, f64)) -> Vec3 {
     let z = 1.0 + r2 * ((1.0 - radius * radius / distance_squared).sqrt() - 1.0);
     let phi = 2.0 * PI * r1;
     let x = phi.cos() * (1.0 - z * z).sqrt();
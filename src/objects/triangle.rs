@@ -6,6 +6,7 @@ use crate::{
     material::Material,
     objects::{Collection, HitRecord, Hittable, Interval, Object},
     ray::Ray,
+    sampler::SampleStream,
 };
 
 const EPSILON: f64 = 1e-8;
@@ -53,7 +54,7 @@ impl Triangle {
 }
 
 impl Hittable for Triangle {
-    fn hit(&self, ray: &Ray, range: Interval) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, range: Interval) -> Option<HitRecord<'_>> {
         let det = self.normal.dot(&ray.direction);
         // No hit if the ray is parallel to the plane.
         if det.abs() < EPSILON {
@@ -85,6 +86,8 @@ impl Hittable for Triangle {
             material: self.material.as_ref(),
             normal,
             front_face,
+            object_id: 0,
+            material_id: 0,
             u: alpha,
             v: beta,
         })
@@ -94,9 +97,9 @@ impl Hittable for Triangle {
         self.bbox
     }
 
-    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, time: f64) -> f64 {
         if let Some(rec) = self.hit(
-            &Ray::new(*origin, *direction),
+            &Ray::time_dependent(*origin, *direction, time),
             Interval::new(0.001, f64::INFINITY),
         ) {
             let distance_squared = rec.t * rec.t * direction.dot(direction);
@@ -106,9 +109,8 @@ impl Hittable for Triangle {
             0.0
         }
     }
-    fn random(&self, origin: &Point3) -> Vec3 {
-        let mut alpha = fastrand::f64();
-        let mut beta = fastrand::f64();
+    fn random(&self, origin: &Point3, _time: f64, samples: &mut SampleStream) -> Vec3 {
+        let (mut alpha, mut beta) = samples.next_2d();
         if alpha + beta > 1.0 {
             alpha = 1.0 - alpha;
             beta = 1.0 - beta;
@@ -123,4 +125,7 @@ impl Hittable for Triangle {
         }
         res
     }
+    fn materials(&self) -> Vec<Material> {
+        vec![self.material.clone()]
+    }
 }
@@ -305,13 +305,10 @@ impl WavefrontObj {
                         &default
                     };
                     let triangle = Triangle::new(q, u, v, mat.clone());
-                    if uv0.is_none() || uv1.is_none() || uv2.is_none() {
-                        triangle
+                    if let (Some(p0), Some(uv1), Some(uv2)) = (uv0, uv1, uv2) {
+                        UVTriangle::new(p0, uv1 - p0, uv2 - p0, triangle)
                     } else {
-                        let p0 = uv0.unwrap();
-                        let p1 = uv1.unwrap() - p0;
-                        let p2 = uv2.unwrap() - p0;
-                        UVTriangle::new(p0, p1, p2, triangle)
+                        triangle
                     }
                 });
                 Ok(surface)
@@ -569,9 +566,10 @@ impl std::fmt::Display for GeometryType {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 enum IlluminationModel {
     // 0. Color on and Ambient off
+    #[default]
     ColourNoAmbient,
     // 1. Color on and Ambient on
     ColourAmbient,
@@ -623,12 +621,6 @@ impl IlluminationModel {
     }
 }
 
-impl Default for IlluminationModel {
-    fn default() -> Self {
-        Self::ColourNoAmbient
-    }
-}
-
 fn path_ref_to_string(path: impl AsRef<Path>) -> String {
     path.as_ref()
         .as_os_str()
@@ -0,0 +1,43 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use image::{ImageError, Rgb32FImage};
+
+use crate::error::RenderError;
+
+const HDR_EXTENSIONS: [&str; 3] = ["exr", "hdr", "pfm"];
+
+pub(crate) fn is_hdr_path(path: impl AsRef<Path>) -> bool {
+    path.as_ref()
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| HDR_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+pub(crate) fn save_hdr(buffer: &Rgb32FImage, path: impl AsRef<Path>) -> Result<(), RenderError> {
+    let is_pfm = path
+        .as_ref()
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("pfm"));
+    if is_pfm {
+        write_pfm(buffer, path).map_err(|e| ImageError::IoError(e).into())
+    } else {
+        buffer.save(path).map_err(|e| e.into())
+    }
+}
+
+fn write_pfm(buffer: &Rgb32FImage, path: impl AsRef<Path>) -> std::io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    // A negative scale marks the pixel data as little endian.
+    write!(file, "PF\n{} {}\n-1.0\n", buffer.width(), buffer.height())?;
+    // PFM stores scanlines from bottom to top.
+    for row in buffer.rows().rev() {
+        for pixel in row {
+            for channel in pixel.0 {
+                file.write_all(&channel.to_le_bytes())?;
+            }
+        }
+    }
+    file.flush()
+}
@@ -0,0 +1,360 @@
+use std::f64::consts::PI;
+
+use image::{Rgb, Rgb32FImage};
+
+use crate::colour::Colour;
+use crate::sampler::{hash, unit};
+
+// Where the image sits on the camera's film, so that effects on a crop line up with the same
+// effects on the full image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FilmWindow {
+    pub x: u32,
+    pub y: u32,
+    pub film_width: u32,
+    pub film_height: u32,
+}
+
+impl FilmWindow {
+    pub fn full(image: &Rgb32FImage) -> Self {
+        Self {
+            x: 0,
+            y: 0,
+            film_width: image.width(),
+            film_height: image.height(),
+        }
+    }
+
+    // The centre of the film, in the coordinates of the image.
+    fn centre(&self) -> (f64, f64) {
+        (
+            self.film_width as f64 / 2.0 - self.x as f64,
+            self.film_height as f64 / 2.0 - self.y as f64,
+        )
+    }
+}
+
+pub trait PostProcess: Send + Sync {
+    fn apply(&self, image: &mut Rgb32FImage, window: &FilmWindow);
+}
+
+impl<F> PostProcess for F
+where
+    F: Fn(&mut Rgb32FImage, &FilmWindow) + Send + Sync,
+{
+    fn apply(&self, image: &mut Rgb32FImage, window: &FilmWindow) {
+        self(image, window)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Bloom {
+    pub threshold: f64,
+    pub intensity: f64,
+    pub radius: f64,
+}
+
+impl Default for Bloom {
+    fn default() -> Self {
+        Self {
+            threshold: 1.0,
+            intensity: 0.1,
+            radius: 8.0,
+        }
+    }
+}
+
+impl PostProcess for Bloom {
+    fn apply(&self, image: &mut Rgb32FImage, _window: &FilmWindow) {
+        let glow = gaussian_blur(&bright_pass(image, self.threshold), self.radius);
+        add_scaled(image, &glow, self.intensity);
+    }
+}
+
+// Star-shaped streaks around bright pixels, as diffraction from the aperture blades.
+#[derive(Debug, Clone, Copy)]
+pub struct Glare {
+    pub threshold: f64,
+    pub intensity: f64,
+    pub streaks: usize,
+    pub length: usize,
+    pub rotation: f64,
+}
+
+impl Default for Glare {
+    fn default() -> Self {
+        Self {
+            threshold: 1.0,
+            intensity: 0.05,
+            streaks: 6,
+            length: 32,
+            rotation: 15.0,
+        }
+    }
+}
+
+impl PostProcess for Glare {
+    fn apply(&self, image: &mut Rgb32FImage, _window: &FilmWindow) {
+        let bright = bright_pass(image, self.threshold);
+        let (width, height) = bright.dimensions();
+        let mut streaks = Rgb32FImage::new(width, height);
+        // Each streak decays to a hundredth of its intensity over its length.
+        let decay = 0.01f64.powf(1.0 / self.length.max(1) as f64);
+        for k in 0..self.streaks {
+            let angle = self.rotation.to_radians() + 2.0 * PI * k as f64 / self.streaks as f64;
+            let (dx, dy) = (angle.cos(), angle.sin());
+            for (x, y, pixel) in streaks.enumerate_pixels_mut() {
+                let mut weight = 1.0 - decay;
+                for step in 1..=self.length {
+                    let sx = (x as f64 + 0.5 - step as f64 * dx).floor();
+                    let sy = (y as f64 + 0.5 - step as f64 * dy).floor();
+                    if sx < 0.0 || sy < 0.0 || sx >= width as f64 || sy >= height as f64 {
+                        break;
+                    }
+                    let source = bright.get_pixel(sx as u32, sy as u32);
+                    for c in 0..3 {
+                        pixel.0[c] += (weight * source.0[c] as f64) as f32;
+                    }
+                    weight *= decay;
+                }
+            }
+        }
+        add_scaled(image, &streaks, self.intensity);
+    }
+}
+
+// Natural vignetting, falling off with the fourth power of the cosine of the field angle.
+#[derive(Debug, Clone, Copy)]
+pub struct Vignette {
+    pub strength: f64,
+}
+
+impl Default for Vignette {
+    fn default() -> Self {
+        Self { strength: 0.8 }
+    }
+}
+
+impl PostProcess for Vignette {
+    fn apply(&self, image: &mut Rgb32FImage, window: &FilmWindow) {
+        let (cx, cy) = window.centre();
+        let (w, h) = (window.film_width as f64, window.film_height as f64);
+        let half_diagonal = (w * w + h * h).sqrt() / 2.0;
+        for (x, y, pixel) in image.enumerate_pixels_mut() {
+            let (dx, dy) = (x as f64 + 0.5 - cx, y as f64 + 0.5 - cy);
+            let tan = self.strength * (dx * dx + dy * dy).sqrt() / half_diagonal;
+            let falloff = 1.0 / (1.0 + tan * tan).powi(2);
+            pixel.0 = pixel.0.map(|c| (c as f64 * falloff) as f32);
+        }
+    }
+}
+
+// Lateral chromatic aberration: red and blue are magnified slightly differently from green.
+#[derive(Debug, Clone, Copy)]
+pub struct ChromaticAberration {
+    pub strength: f64,
+}
+
+impl Default for ChromaticAberration {
+    fn default() -> Self {
+        Self { strength: 0.005 }
+    }
+}
+
+impl PostProcess for ChromaticAberration {
+    fn apply(&self, image: &mut Rgb32FImage, window: &FilmWindow) {
+        let source = image.clone();
+        let (cx, cy) = window.centre();
+        for (x, y, pixel) in image.enumerate_pixels_mut() {
+            let (dx, dy) = (x as f64 + 0.5 - cx, y as f64 + 0.5 - cy);
+            for (c, scale) in [(0, 1.0 + self.strength), (2, 1.0 - self.strength)] {
+                let sample = bilinear(&source, cx + dx / scale - 0.5, cy + dy / scale - 0.5);
+                pixel.0[c] = sample.0[c];
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct FilmGrain {
+    pub amount: f64,
+    pub seed: u64,
+}
+
+impl Default for FilmGrain {
+    fn default() -> Self {
+        Self {
+            amount: 0.05,
+            seed: 0,
+        }
+    }
+}
+
+impl PostProcess for FilmGrain {
+    fn apply(&self, image: &mut Rgb32FImage, window: &FilmWindow) {
+        for (x, y, pixel) in image.enumerate_pixels_mut() {
+            let (x, y) = (x + window.x, y + window.y);
+            // The sum of four uniforms is close enough to a normal distribution for grain.
+            let noise: f64 = (0..4)
+                .map(|k| unit(hash(&[self.seed, x as u64, y as u64, k])))
+                .sum::<f64>()
+                - 2.0;
+            let grain = 1.0 + self.amount * noise * 3f64.sqrt();
+            pixel.0 = pixel.0.map(|c| (c as f64 * grain).max(0.0) as f32);
+        }
+    }
+}
+
+// Unsharp masking: adds back the difference between the image and a blurred copy.
+#[derive(Debug, Clone, Copy)]
+pub struct Sharpen {
+    pub amount: f64,
+    pub radius: f64,
+}
+
+impl Default for Sharpen {
+    fn default() -> Self {
+        Self {
+            amount: 0.5,
+            radius: 1.0,
+        }
+    }
+}
+
+impl PostProcess for Sharpen {
+    fn apply(&self, image: &mut Rgb32FImage, _window: &FilmWindow) {
+        let blurred = gaussian_blur(image, self.radius);
+        for (pixel, blur) in image.pixels_mut().zip(blurred.pixels()) {
+            for c in 0..3 {
+                let detail = pixel.0[c] as f64 - blur.0[c] as f64;
+                pixel.0[c] = (pixel.0[c] as f64 + self.amount * detail).max(0.0) as f32;
+            }
+        }
+    }
+}
+
+// The part of each pixel brighter than the threshold, keeping its hue.
+fn bright_pass(image: &Rgb32FImage, threshold: f64) -> Rgb32FImage {
+    let mut bright = image.clone();
+    for pixel in bright.pixels_mut() {
+        let luminance = Colour::from(&*pixel).luminance();
+        let excess = if luminance > threshold {
+            (luminance - threshold) / luminance
+        } else {
+            0.0
+        };
+        pixel.0 = pixel.0.map(|c| (c as f64 * excess) as f32);
+    }
+    bright
+}
+
+fn add_scaled(image: &mut Rgb32FImage, other: &Rgb32FImage, scale: f64) {
+    for (pixel, add) in image.pixels_mut().zip(other.pixels()) {
+        for c in 0..3 {
+            pixel.0[c] += (scale * add.0[c] as f64) as f32;
+        }
+    }
+}
+
+// Separable Gaussian with a standard deviation of `sigma` pixels, clamping at the borders.
+fn gaussian_blur(image: &Rgb32FImage, sigma: f64) -> Rgb32FImage {
+    if sigma <= 0.0 {
+        return image.clone();
+    }
+    let radius = (3.0 * sigma).ceil() as i64;
+    let kernel: Vec<f64> = (-radius..=radius)
+        .map(|k| (-(k * k) as f64 / (2.0 * sigma * sigma)).exp())
+        .collect();
+    let total: f64 = kernel.iter().sum();
+    let (width, height) = image.dimensions();
+    let pass = |source: &Rgb32FImage, dx: i64, dy: i64| {
+        Rgb32FImage::from_fn(width, height, |x, y| {
+            let mut sum = [0.0; 3];
+            for (k, weight) in kernel.iter().enumerate() {
+                let offset = k as i64 - radius;
+                let sx = (x as i64 + offset * dx).clamp(0, width as i64 - 1);
+                let sy = (y as i64 + offset * dy).clamp(0, height as i64 - 1);
+                let p = source.get_pixel(sx as u32, sy as u32);
+                for (s, c) in sum.iter_mut().zip(p.0) {
+                    *s += weight * c as f64;
+                }
+            }
+            Rgb(sum.map(|s| (s / total) as f32))
+        })
+    };
+    pass(&pass(image, 1, 0), 0, 1)
+}
+
+fn bilinear(image: &Rgb32FImage, x: f64, y: f64) -> Rgb<f32> {
+    let (width, height) = (image.width() as i64, image.height() as i64);
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let texel = |x: f64, y: f64| {
+        let x = (x as i64).clamp(0, width - 1) as u32;
+        let y = (y as i64).clamp(0, height - 1) as u32;
+        image.get_pixel(x, y).0.map(|c| c as f64)
+    };
+    let (a, b) = (texel(x0, y0), texel(x0 + 1.0, y0));
+    let (c, d) = (texel(x0, y0 + 1.0), texel(x0 + 1.0, y0 + 1.0));
+    Rgb(std::array::from_fn(|k| {
+        let top = a[k] + fx * (b[k] - a[k]);
+        let bottom = c[k] + fx * (d[k] - c[k]);
+        (top + fy * (bottom - top)) as f32
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use image::imageops::crop_imm;
+
+    use super::*;
+
+    fn film() -> Rgb32FImage {
+        Rgb32FImage::from_fn(64, 48, |x, y| {
+            Rgb([x as f32 / 64.0, y as f32 / 48.0, ((x * y) % 7) as f32 / 7.0])
+        })
+    }
+
+    // Applies the effect to the full film and to a crop of it, returning both for the crop.
+    fn full_and_cropped(effect: &dyn PostProcess) -> (Rgb32FImage, Rgb32FImage) {
+        let (x, y, width, height) = (40, 6, 20, 16);
+        let mut full = film();
+        let window = FilmWindow::full(&full);
+        effect.apply(&mut full, &window);
+        let mut crop = crop_imm(&film(), x, y, width, height).to_image();
+        let window = FilmWindow {
+            x,
+            y,
+            film_width: 64,
+            film_height: 48,
+        };
+        effect.apply(&mut crop, &window);
+        (crop_imm(&full, x, y, width, height).to_image(), crop)
+    }
+
+    #[test]
+    fn crops_are_processed_in_film_coordinates() {
+        let (full, crop) = full_and_cropped(&Vignette::default());
+        assert_eq!(full, crop);
+        let (full, crop) = full_and_cropped(&FilmGrain::default());
+        assert_eq!(full, crop);
+        // Colours shifted in from outside the crop are only missing along its border.
+        let (full, crop) = full_and_cropped(&ChromaticAberration { strength: 0.02 });
+        for y in 1..crop.height() - 1 {
+            for x in 1..crop.width() - 1 {
+                assert_eq!(full.get_pixel(x, y), crop.get_pixel(x, y), "at ({x}, {y})");
+            }
+        }
+    }
+
+    #[test]
+    fn vignette_darkens_towards_the_corners() {
+        let mut image = Rgb32FImage::from_pixel(64, 48, Rgb([1.0; 3]));
+        let window = FilmWindow::full(&image);
+        Vignette::default().apply(&mut image, &window);
+        let centre = image.get_pixel(32, 24).0[0];
+        assert!((centre - 1.0).abs() < 1e-3, "{centre}");
+        assert!(image.get_pixel(0, 0).0[0] < image.get_pixel(16, 12).0[0]);
+        assert!(image.get_pixel(16, 12).0[0] < centre);
+    }
+}
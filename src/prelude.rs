@@ -2,6 +2,7 @@ pub use crate::colour::{Color, Colour};
 pub use crate::error::RenderError;
 pub use crate::linalg::{Point3, Vec3};
 pub use crate::objects::Collection;
-pub use crate::render::Camera;
+pub use crate::ray::Ray;
+pub use crate::render::{Camera, Projection};
 pub use crate::scene::Scene;
 pub use image::ImageError;
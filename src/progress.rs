@@ -0,0 +1,145 @@
+use std::io::{Write, stderr};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::statistics::RenderStatistics;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    CollectingLights,
+    BuildingHierarchy,
+    Tracing { pass: usize },
+    Denoising,
+    PostProcessing,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum ProgressEvent<'a> {
+    PhaseStarted(Phase),
+    HierarchyBuilt {
+        nodes: usize,
+        primitives: usize,
+    },
+    ImageSplit {
+        blocks: usize,
+    },
+    CheckpointOpened {
+        path: &'a Path,
+    },
+    BlockFinished {
+        xmin: usize,
+        xmax: usize,
+        ymin: usize,
+        ymax: usize,
+        elapsed: Duration,
+        finished: usize,
+        total: usize,
+    },
+    PassFinished {
+        pass: usize,
+        samples: usize,
+    },
+    TracingFinished,
+    Cancelled,
+    TimeBudgetExhausted,
+    RenderFinished {
+        statistics: &'a RenderStatistics,
+    },
+    Saving {
+        path: &'a Path,
+    },
+}
+
+pub trait ProgressObserver: Send + Sync {
+    fn notify(&self, event: &ProgressEvent);
+}
+
+impl<F> ProgressObserver for F
+where
+    F: Fn(&ProgressEvent) + Send + Sync,
+{
+    fn notify(&self, event: &ProgressEvent) {
+        self(event)
+    }
+}
+
+// Keeps the tracing progress on one line, redrawn at most every `UPDATE_INTERVAL`.
+#[derive(Debug, Default)]
+pub struct TerminalProgress {
+    line: Mutex<ProgressLine>,
+}
+
+#[derive(Debug, Default)]
+struct ProgressLine {
+    pass: usize,
+    last_update: Option<Instant>,
+}
+
+impl TerminalProgress {
+    const UPDATE_INTERVAL: Duration = Duration::from_millis(100);
+}
+
+impl ProgressObserver for TerminalProgress {
+    fn notify(&self, event: &ProgressEvent) {
+        let mut p = stderr().lock();
+        match event {
+            ProgressEvent::PhaseStarted(Phase::CollectingLights) => {
+                writeln!(p, "Collecting light sources...")
+            }
+            ProgressEvent::PhaseStarted(Phase::BuildingHierarchy) => {
+                writeln!(p, "Building render node hierarchy...")
+            }
+            ProgressEvent::PhaseStarted(Phase::Tracing { pass }) => {
+                self.line.lock().unwrap().pass = *pass;
+                Ok(())
+            }
+            ProgressEvent::PhaseStarted(Phase::Denoising) => writeln!(p, "Denoising..."),
+            ProgressEvent::PhaseStarted(Phase::PostProcessing) => {
+                writeln!(p, "Post-processing...")
+            }
+            ProgressEvent::HierarchyBuilt { nodes, primitives } => {
+                writeln!(p, "Built {nodes} nodes over {primitives} objects...")
+            }
+            ProgressEvent::ImageSplit { blocks } => {
+                writeln!(p, "Split target image into {blocks} blocks...")
+            }
+            ProgressEvent::CheckpointOpened { path } => {
+                writeln!(p, "Checkpointing to {}...", path.display())
+            }
+            ProgressEvent::BlockFinished {
+                finished, total, ..
+            } => {
+                let mut line = self.line.lock().unwrap();
+                if line
+                    .last_update
+                    .is_some_and(|last| last.elapsed() < Self::UPDATE_INTERVAL)
+                {
+                    return;
+                }
+                line.last_update = Some(Instant::now());
+                write!(
+                    p,
+                    "\rPass {}: {:.2}% done...",
+                    line.pass,
+                    *finished as f64 / *total as f64 * 100.0
+                )
+            }
+            ProgressEvent::PassFinished { .. } => Ok(()),
+            ProgressEvent::TracingFinished => {
+                writeln!(p, "\rRay tracing done.                             ")
+            }
+            ProgressEvent::Cancelled => writeln!(p, "Rendering cancelled."),
+            ProgressEvent::TimeBudgetExhausted => writeln!(p, "Time budget exhausted."),
+            ProgressEvent::RenderFinished { statistics } => writeln!(
+                p,
+                "Traced {} rays and {} shadow rays along {} paths in {:.2?}.",
+                statistics.rays, statistics.shadow_rays, statistics.paths, statistics.total_time
+            ),
+            ProgressEvent::Saving { path } => {
+                writeln!(p, "Saving image to {}...", path.display())
+            }
+        }
+        .unwrap();
+    }
+}
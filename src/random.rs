@@ -3,18 +3,15 @@ use std::f64::consts::PI;
 use crate::{
     linalg::{ONB, Point3, Vec3},
     objects::Hittable,
+    sampler::SampleStream,
 };
 
-pub fn sample_square() -> Vec3 {
-    Vec3::new(fastrand::f64() - 0.5, fastrand::f64() - 0.5, 0.0)
-}
-
-pub fn random_unit_vector() -> Vec3 {
+pub fn random_unit_vector(rng: &mut fastrand::Rng) -> Vec3 {
     loop {
         let p = Vec3::new(
-            2.0 * fastrand::f64() - 1.0,
-            2.0 * fastrand::f64() - 1.0,
-            2.0 * fastrand::f64() - 1.0,
+            2.0 * rng.f64() - 1.0,
+            2.0 * rng.f64() - 1.0,
+            2.0 * rng.f64() - 1.0,
         );
         let lp = p.dot(&p);
         if lp > 1e-160 && lp <= 1.0 {
@@ -23,18 +20,28 @@ pub fn random_unit_vector() -> Vec3 {
     }
 }
 
-pub fn random_unit_disk() -> Vec3 {
-    loop {
-        let p = 2.0 * sample_square();
-        if p.dot(&p) < 1.0 {
-            return p;
-        }
+// Shirley and Chiu's concentric mapping, which keeps strata of the square contiguous on the disk.
+pub fn concentric_disk((u, v): (f64, f64)) -> Vec3 {
+    let (a, b) = (2.0 * u - 1.0, 2.0 * v - 1.0);
+    if a == 0.0 && b == 0.0 {
+        return Vec3::ZERO;
     }
+    let (r, phi) = if a.abs() > b.abs() {
+        (a, PI / 4.0 * (b / a))
+    } else {
+        (b, PI / 2.0 - PI / 4.0 * (a / b))
+    };
+    Vec3::new(r * phi.cos(), r * phi.sin(), 0.0)
+}
+
+pub fn uniform_sphere((u, v): (f64, f64)) -> Vec3 {
+    let z = 1.0 - 2.0 * u;
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * PI * v;
+    Vec3::new(r * phi.cos(), r * phi.sin(), z)
 }
 
-pub fn random_cosine_direction() -> Vec3 {
-    let r1 = fastrand::f64();
-    let r2 = fastrand::f64();
+pub fn cosine_direction((r1, r2): (f64, f64)) -> Vec3 {
     let phi = 2.0 * PI * r1;
     let x = phi.cos() * r2.sqrt();
     let y = phi.sin() * r2.sqrt();
@@ -44,7 +51,7 @@ pub fn random_cosine_direction() -> Vec3 {
 
 pub trait DirectionalPDF: Send + Sync {
     fn value(&self, direction: &Vec3) -> f64;
-    fn generate(&self) -> Vec3;
+    fn generate(&self, samples: &mut SampleStream) -> Vec3;
 }
 
 pub struct UniformSphericalPDF;
@@ -53,8 +60,8 @@ impl DirectionalPDF for UniformSphericalPDF {
     fn value(&self, _direction: &Vec3) -> f64 {
         1.0 / (4.0 * PI)
     }
-    fn generate(&self) -> Vec3 {
-        random_unit_vector()
+    fn generate(&self, samples: &mut SampleStream) -> Vec3 {
+        uniform_sphere(samples.next_2d())
     }
 }
 
@@ -75,51 +82,32 @@ impl DirectionalPDF for CosinePDF {
         let cosine_theta = direction.normalize().dot(&self.uvw.w);
         (cosine_theta / PI).max(0.0)
     }
-    fn generate(&self) -> Vec3 {
-        self.uvw.transform(&random_cosine_direction())
+    fn generate(&self, samples: &mut SampleStream) -> Vec3 {
+        self.uvw.transform(&cosine_direction(samples.next_2d()))
     }
 }
 
 pub struct HittablePDF<'a> {
     objects: &'a dyn Hittable,
     origin: Point3,
+    time: f64,
 }
 
 impl<'a> HittablePDF<'a> {
-    pub fn new(objects: &'a dyn Hittable, origin: Point3) -> Self {
-        Self { objects, origin }
+    pub fn new(objects: &'a dyn Hittable, origin: Point3, time: f64) -> Self {
+        Self {
+            objects,
+            origin,
+            time,
+        }
     }
 }
 
 impl<'a> DirectionalPDF for HittablePDF<'a> {
     fn value(&self, direction: &Vec3) -> f64 {
-        self.objects.pdf_value(&self.origin, direction)
+        self.objects.pdf_value(&self.origin, direction, self.time)
     }
-    fn generate(&self) -> Vec3 {
-        self.objects.random(&self.origin)
-    }
-}
-
-pub struct MixturePDF<'a> {
-    p0: &'a dyn DirectionalPDF,
-    p1: &'a dyn DirectionalPDF,
-}
-
-impl<'a> MixturePDF<'a> {
-    pub fn new(p0: &'a dyn DirectionalPDF, p1: &'a dyn DirectionalPDF) -> Self {
-        Self { p0, p1 }
-    }
-}
-
-impl<'a> DirectionalPDF for MixturePDF<'a> {
-    fn value(&self, direction: &Vec3) -> f64 {
-        0.5 * self.p0.value(direction) + 0.5 * self.p1.value(direction)
-    }
-    fn generate(&self) -> Vec3 {
-        if fastrand::f64() < 0.5 {
-            self.p0.generate()
-        } else {
-            self.p1.generate()
-        }
+    fn generate(&self, samples: &mut SampleStream) -> Vec3 {
+        self.objects.random(&self.origin, self.time, samples)
     }
 }
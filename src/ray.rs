@@ -5,6 +5,8 @@ pub struct Ray {
     pub origin: Point3,
     pub direction: Vec3,
     pub time: f64,
+    // Set through `with_sample`, and carried along when the ray is moved into object space.
+    sample: Option<f64>,
 }
 
 impl Ray {
@@ -13,6 +15,7 @@ impl Ray {
             origin,
             direction,
             time: 0.0,
+            sample: None,
         }
     }
 
@@ -21,6 +24,27 @@ impl Ray {
             origin,
             direction,
             time,
+            sample: None,
+        }
+    }
+
+    // A sample from the path's stream, for media that decide where the ray scatters while it is
+    // being intersected.
+    pub fn with_sample(mut self, u: f64) -> Self {
+        self.sample = Some(u);
+        self
+    }
+
+    pub fn sample(&self) -> Option<f64> {
+        self.sample
+    }
+
+    // The same ray in another frame, keeping its time and sample.
+    pub fn transformed(&self, origin: Point3, direction: Vec3) -> Self {
+        Self {
+            origin,
+            direction,
+            ..*self
         }
     }
 
@@ -0,0 +1,150 @@
+use std::f64::consts::PI;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReconstructionFilter {
+    Box { radius: f64 },
+    Gaussian { radius: f64, sigma: f64 },
+    Mitchell { radius: f64, b: f64, c: f64 },
+    Lanczos { radius: f64 },
+    BlackmanHarris { radius: f64 },
+}
+
+impl Default for ReconstructionFilter {
+    fn default() -> Self {
+        Self::Box { radius: 0.5 }
+    }
+}
+
+impl ReconstructionFilter {
+    pub(crate) fn radius(&self) -> f64 {
+        match *self {
+            Self::Box { radius }
+            | Self::Gaussian { radius, .. }
+            | Self::Mitchell { radius, .. }
+            | Self::Lanczos { radius }
+            | Self::BlackmanHarris { radius } => radius,
+        }
+    }
+
+    // Pixels on either side of a pixel that a sample inside it can reach.
+    pub(crate) fn margin(&self) -> usize {
+        (self.radius() - 0.5).ceil().max(0.0) as usize
+    }
+
+    // The weight of a sample at offset (dx, dy) from a pixel centre.
+    pub(crate) fn weight(&self, dx: f64, dy: f64) -> f64 {
+        self.evaluate(dx) * self.evaluate(dy)
+    }
+
+    fn evaluate(&self, x: f64) -> f64 {
+        let radius = self.radius();
+        // Half-open, so a sample on the border between two pixels only counts once.
+        if x < -radius || x >= radius {
+            return 0.0;
+        }
+        match *self {
+            Self::Box { .. } => 1.0,
+            Self::Gaussian { sigma, .. } => {
+                let gaussian = |x: f64| (-x * x / (2.0 * sigma * sigma)).exp();
+                (gaussian(x) - gaussian(radius)).max(0.0)
+            }
+            Self::Mitchell { b, c, .. } => {
+                let x = (2.0 * x / radius).abs();
+                if x > 1.0 {
+                    ((-b - 6.0 * c) * x * x * x
+                        + (6.0 * b + 30.0 * c) * x * x
+                        + (-12.0 * b - 48.0 * c) * x
+                        + (8.0 * b + 24.0 * c))
+                        / 6.0
+                } else {
+                    ((12.0 - 9.0 * b - 6.0 * c) * x * x * x
+                        + (-18.0 + 12.0 * b + 6.0 * c) * x * x
+                        + (6.0 - 2.0 * b))
+                        / 6.0
+                }
+            }
+            Self::Lanczos { .. } => sinc(x) * sinc(x / radius),
+            Self::BlackmanHarris { .. } => {
+                let t = 2.0 * PI * (x / (2.0 * radius) + 0.5);
+                0.35875 - 0.48829 * t.cos() + 0.14128 * (2.0 * t).cos() - 0.01168 * (3.0 * t).cos()
+            }
+        }
+    }
+}
+
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-5 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILTERS: [ReconstructionFilter; 5] = [
+        ReconstructionFilter::Box { radius: 0.5 },
+        ReconstructionFilter::Gaussian {
+            radius: 1.5,
+            sigma: 0.5,
+        },
+        ReconstructionFilter::Mitchell {
+            radius: 2.0,
+            b: 1.0 / 3.0,
+            c: 1.0 / 3.0,
+        },
+        ReconstructionFilter::Lanczos { radius: 3.0 },
+        ReconstructionFilter::BlackmanHarris { radius: 1.5 },
+    ];
+
+    #[test]
+    fn weights_vanish_outside_the_radius_and_peak_at_the_centre() {
+        for filter in FILTERS {
+            let radius = filter.radius();
+            assert_eq!(filter.weight(radius, 0.0), 0.0, "{filter:?}");
+            assert_eq!(filter.weight(0.0, -radius - 0.1), 0.0, "{filter:?}");
+            let centre = filter.weight(0.0, 0.0);
+            assert!(centre > 0.0, "{filter:?}");
+            for k in 1..20 {
+                let x = radius * k as f64 / 20.0;
+                assert!(filter.weight(x, 0.0) <= centre, "{filter:?} at {x}");
+                assert!((filter.weight(x, 0.0) - filter.weight(-x, 0.0)).abs() < 1e-12);
+                assert_eq!(filter.weight(x, 0.3), filter.weight(0.3, x));
+            }
+        }
+    }
+
+    #[test]
+    fn box_weights_count_each_sample_once() {
+        let filter = ReconstructionFilter::default();
+        for k in 0..10 {
+            let offset = k as f64 / 10.0 - 0.5;
+            let total: f64 = (-2..=2)
+                .map(|p| filter.weight(offset - p as f64, 0.0))
+                .sum();
+            assert_eq!(total, 1.0, "offset {offset}");
+        }
+        assert_eq!(filter.weight(-0.5, 0.0), 1.0);
+        assert_eq!(filter.weight(0.5, 0.0), 0.0);
+    }
+
+    #[test]
+    fn mitchell_weights_sum_to_one_across_pixels() {
+        for (b, c) in [(1.0 / 3.0, 1.0 / 3.0), (0.0, 0.5), (1.0, 0.0)] {
+            let filter = ReconstructionFilter::Mitchell { radius: 2.0, b, c };
+            for k in 0..10 {
+                let offset = k as f64 / 10.0 - 0.5;
+                let total: f64 = (-3..=3).map(|p| filter.evaluate(offset - p as f64)).sum();
+                assert!((total - 1.0).abs() < 1e-12, "B={b} C={c} offset {offset}");
+            }
+        }
+    }
+
+    #[test]
+    fn margins_cover_the_filter_footprint() {
+        let margins: Vec<usize> = FILTERS.iter().map(|filter| filter.margin()).collect();
+        assert_eq!(margins, [0, 1, 2, 3, 1]);
+    }
+}
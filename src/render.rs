@@ -1,25 +1,40 @@
+use std::collections::HashMap;
 use std::f64;
-use std::fmt::Display;
-use std::io::Write;
+use std::fmt::Debug;
+use std::ops::{AddAssign, ControlFlow};
 use std::path::Path;
-use std::sync::atomic::AtomicUsize;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
 
 use image::buffer::ConvertBuffer;
 use image::{Rgb32FImage, RgbImage};
 use rayon::iter::{IntoParallelRefMutIterator, ParallelIterator};
 
-use crate::bounding_box::BVHNode;
+use crate::adaptive::{AdaptiveSampling, RunningVariance};
+use crate::aov::{Aov, AovPixel};
+use crate::bounding_box::{AaBb, BVHNode};
+use crate::cancel::CancellationToken;
+use crate::checkpoint::{Checkpoint, CheckpointState, Decoder, Encoder, Fingerprint};
 use crate::colour::Colour;
+use crate::denoise::{Denoiser, Features};
+use crate::display::DisplayTransform;
 use crate::effects::{RenderFilter, TrivialFilter};
 use crate::error::RenderError;
+use crate::integrator::{Integrator, PathTracer, Radiance, RussianRoulette, TraceContext};
+use crate::lens::Aperture;
 use crate::linalg::{Point3, Vec3};
-use crate::material::ScatterResult;
-use crate::objects::{Hittable, Interval, sphere_uv};
-use crate::random::{DirectionalPDF, HittablePDF, MixturePDF, random_unit_disk};
+use crate::material::{Material, Scatter};
+use crate::objects::{Collection, HitRecord, Hittable, Interval};
+use crate::output::{is_hdr_path, save_hdr};
+use crate::postprocess::{FilmWindow, PostProcess};
+use crate::progress::{Phase, ProgressEvent, ProgressObserver, TerminalProgress};
 use crate::ray::Ray;
+use crate::reconstruction::ReconstructionFilter;
+use crate::sampler::{SampleStream, Sampler, StratifiedSampler};
 use crate::scene::Scene;
-use crate::texture::{SkyTexture, Texture, Textured};
+use crate::statistics::{self, Counter, Counters, RenderStatistics};
+use crate::texture::{SkyTexture, Texture};
 
 #[derive(Debug, Clone)]
 pub struct Camera {
@@ -32,13 +47,64 @@ pub struct Camera {
     pub aspect_ratio: f64,
     pub image_width: usize,
     pub background: Texture,
+    pub projection: Projection,
+    pub shutter_open: f64,
+    pub shutter_close: f64,
+    pub shutter_curve: ShutterCurve,
+    pub aperture: Aperture,
+    pub cats_eye: f64,
+    pub shift_x: f64,
+    pub shift_y: f64,
+    pub tilt_x: f64,
+    pub tilt_y: f64,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum ShutterCurve {
+    #[default]
+    Box,
+    Triangle,
+    Trapezoid {
+        ramp: f64,
+    },
+}
+
+impl ShutterCurve {
+    // Maps a uniform sample to a fraction of the shutter interval, distributed like the curve.
+    fn sample(&self, u: f64) -> f64 {
+        let ramp = match *self {
+            ShutterCurve::Box => return u,
+            ShutterCurve::Triangle => 0.5,
+            ShutterCurve::Trapezoid { ramp } => ramp.clamp(0.0, 0.5),
+        };
+        let area = u * (1.0 - ramp);
+        if area < 0.5 * ramp {
+            (2.0 * ramp * area).sqrt()
+        } else if area < 1.0 - 1.5 * ramp {
+            area + 0.5 * ramp
+        } else {
+            1.0 - (2.0 * ramp * (1.0 - ramp - area)).max(0.0).sqrt()
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Projection {
+    #[default]
+    Perspective,
+    Orthographic {
+        width: f64,
+    },
+    Equirectangular,
+    Fisheye {
+        fov: f64,
+    },
 }
 
 pub struct Renderer {
     image_width: usize,
     image_height: usize,
-    sqrt_spp: usize,
-    recip_sqrt_spp: f64,
+    samples_per_pixel: usize,
     pixel00_loc: Point3,
     pixel_delta_u: Vec3,
     pixel_delta_v: Vec3,
@@ -46,7 +112,90 @@ pub struct Renderer {
     defocus_disk_u: Vec3,
     defocus_disk_v: Vec3,
     pub(crate) center: Point3,
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+    focus_dist: f64,
+    focus_normal: Vec3,
+    projection: Projection,
+    ods_offset: f64,
+    shutter_open: f64,
+    shutter_close: f64,
+    shutter_curve: ShutterCurve,
+    aperture: Aperture,
+    cats_eye: f64,
     background: Texture,
+    aovs: Vec<Aov>,
+    denoiser: Option<Denoiser>,
+    adaptive: Option<AdaptiveSampling>,
+    checkpoint: Option<Checkpoint>,
+    cancellation: Option<CancellationToken>,
+    time_budget: Option<Duration>,
+    progress: Arc<dyn ProgressObserver>,
+    crop: Option<Crop>,
+    sampler: Option<Arc<dyn Sampler>>,
+    seed: u64,
+    reconstruction: ReconstructionFilter,
+    display: DisplayTransform,
+    post_processes: Vec<Arc<dyn PostProcess>>,
+    integrator: Arc<dyn Integrator>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Crop {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+    pub full_frame: bool,
+}
+
+impl Crop {
+    pub fn new(x: usize, y: usize, width: usize, height: usize) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+            full_frame: false,
+        }
+    }
+
+    pub fn in_full_frame(self) -> Self {
+        Self {
+            full_frame: true,
+            ..self
+        }
+    }
+}
+
+pub struct Frame {
+    pub image: Rgb32FImage,
+    pub aovs: HashMap<Aov, Rgb32FImage>,
+    pub complete: bool,
+    pub statistics: RenderStatistics,
+}
+
+type PassCallback<'a> = dyn FnMut(usize, &Rgb32FImage) -> ControlFlow<()> + 'a;
+
+struct RenderContext<'a> {
+    scene: TraceContext<'a>,
+    aovs: &'a [Aov],
+    deadline: Option<Instant>,
+    cancellation: Option<&'a CancellationToken>,
+}
+
+impl RenderContext<'_> {
+    fn interrupted(&self) -> bool {
+        self.cancelled()
+            || self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    fn cancelled(&self) -> bool {
+        self.cancellation.is_some_and(|token| token.is_cancelled())
+    }
 }
 
 impl Default for Camera {
@@ -61,6 +210,16 @@ impl Default for Camera {
             aspect_ratio: 16.0 / 9.0,
             image_width: 400,
             background: SkyTexture::default(),
+            projection: Projection::default(),
+            shutter_open: 0.0,
+            shutter_close: 1.0,
+            shutter_curve: ShutterCurve::default(),
+            aperture: Aperture::default(),
+            cats_eye: 0.0,
+            shift_x: 0.0,
+            shift_y: 0.0,
+            tilt_x: 0.0,
+            tilt_y: 0.0,
         }
     }
 }
@@ -69,10 +228,16 @@ impl Camera {
     pub fn renderer(&self, samples_per_pixel: usize, max_depth: usize) -> Renderer {
         let image_height = ((self.image_width as f64 / self.aspect_ratio) as usize).max(1);
 
-        let theta = self.vfov.to_radians();
-        let h = (theta / 2.0).tan();
-        let viewport_height = 2.0 * h * self.focus_dist;
-        let viewport_width = viewport_height * (self.image_width as f64 / image_height as f64);
+        let aspect = self.image_width as f64 / image_height as f64;
+        let viewport_height = match self.projection {
+            Projection::Orthographic { width } => width / aspect,
+            _ => {
+                let theta = self.vfov.to_radians();
+                let h = (theta / 2.0).tan();
+                2.0 * h * self.focus_dist
+            }
+        };
+        let viewport_width = viewport_height * aspect;
 
         let w = (self.lookfrom - self.lookat).normalize();
         let u = self.up.cross(&w).normalize();
@@ -86,21 +251,23 @@ impl Camera {
         let pixel_delta_u = viewport_u / self.image_width as f64;
         let pixel_delta_v = viewport_v / image_height as f64;
 
-        // Calculate the location of the upper left pixel.
-        let viewport_upper_left =
-            self.lookfrom - self.focus_dist * w - viewport_u / 2.0 - viewport_v / 2.0;
+        // Calculate the location of the upper left pixel, moved off-axis by the lens shift.
+        let viewport_center = self.lookfrom - self.focus_dist * w + self.shift_x * viewport_u
+            - self.shift_y * viewport_v;
+        let viewport_upper_left = viewport_center - viewport_u / 2.0 - viewport_v / 2.0;
         let pixel00_loc = viewport_upper_left + 0.5 * (pixel_delta_u + pixel_delta_v);
         let defocus_radius = self.focus_dist * (self.defocus_angle / 2.0).to_radians().tan();
         let defocus_disk_u = u * defocus_radius;
         let defocus_disk_v = v * defocus_radius;
-
-        let sqrt_spp = (samples_per_pixel as f64).sqrt() as usize;
+        // Tilting the lens tilts the plane of focus away from the viewport.
+        let focus_normal =
+            (w + self.tilt_x.to_radians().tan() * v + self.tilt_y.to_radians().tan() * u)
+                .normalize();
 
         Renderer {
             image_width: self.image_width,
             image_height,
-            sqrt_spp,
-            recip_sqrt_spp: 1.0 / sqrt_spp as f64,
+            samples_per_pixel,
             pixel00_loc,
             pixel_delta_u,
             pixel_delta_v,
@@ -108,206 +275,1278 @@ impl Camera {
             defocus_disk_u,
             defocus_disk_v,
             center: self.lookfrom,
+            u,
+            v,
+            w,
+            focus_dist: self.focus_dist,
+            focus_normal,
+            projection: self.projection,
+            ods_offset: 0.0,
+            shutter_open: self.shutter_open,
+            shutter_close: self.shutter_close,
+            shutter_curve: self.shutter_curve,
+            aperture: self.aperture.clone(),
+            cats_eye: self.cats_eye,
             background: self.background.clone(),
+            aovs: Vec::new(),
+            denoiser: None,
+            adaptive: None,
+            checkpoint: None,
+            cancellation: None,
+            time_budget: None,
+            progress: Arc::new(TerminalProgress::default()),
+            crop: None,
+            sampler: None,
+            seed: 0,
+            reconstruction: ReconstructionFilter::default(),
+            display: DisplayTransform::default(),
+            post_processes: Vec::new(),
+            integrator: Arc::new(PathTracer::default()),
         }
     }
 }
 
 impl Renderer {
     const BLOCK_SIZE: usize = 64;
-    fn get_ray(&self, x: usize, y: usize, si: usize, sj: usize) -> Ray {
-        let offset = self.sample_square_stratified(si, sj);
+
+    pub fn with_aovs(mut self, aovs: &[Aov]) -> Self {
+        self.aovs = aovs.to_vec();
+        self.aovs.sort();
+        self.aovs.dedup();
+        self
+    }
+
+    pub fn with_denoiser(mut self, denoiser: Denoiser) -> Self {
+        self.denoiser = Some(denoiser);
+        self
+    }
+
+    pub fn with_adaptive_sampling(mut self, adaptive: AdaptiveSampling) -> Self {
+        self.adaptive = Some(adaptive);
+        self
+    }
+
+    pub fn with_checkpoint(mut self, checkpoint: Checkpoint) -> Self {
+        self.checkpoint = Some(checkpoint);
+        self
+    }
+
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    pub fn with_time_budget(mut self, budget: Duration) -> Self {
+        self.time_budget = Some(budget);
+        self
+    }
+
+    pub fn with_progress(mut self, progress: impl ProgressObserver + 'static) -> Self {
+        self.progress = Arc::new(progress);
+        self
+    }
+
+    pub fn with_crop(mut self, crop: Crop) -> Self {
+        let x = crop.x.min(self.image_width);
+        let y = crop.y.min(self.image_height);
+        self.crop = Some(Crop {
+            x,
+            y,
+            width: crop.width.min(self.image_width - x),
+            height: crop.height.min(self.image_height - y),
+            ..crop
+        });
+        self
+    }
+
+    pub fn with_sampler(mut self, sampler: impl Sampler + 'static) -> Self {
+        self.sampler = Some(Arc::new(sampler));
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn with_reconstruction_filter(mut self, filter: ReconstructionFilter) -> Self {
+        self.reconstruction = filter;
+        self
+    }
+
+    pub fn with_display_transform(mut self, display: DisplayTransform) -> Self {
+        self.display = display;
+        self
+    }
+
+    // Effects run in the order they are added, on the linear image before tone mapping.
+    pub fn with_post_process(mut self, effect: impl PostProcess + 'static) -> Self {
+        self.post_processes.push(Arc::new(effect));
+        self
+    }
+
+    pub fn with_integrator(mut self, integrator: impl Integrator + 'static) -> Self {
+        self.integrator = Arc::new(integrator);
+        self
+    }
+
+    pub fn with_russian_roulette(self, roulette: RussianRoulette) -> Self {
+        self.with_integrator(PathTracer { roulette })
+    }
+
+    pub(crate) fn shift_viewport(mut self, offset: Vec3) -> Self {
+        self.pixel00_loc += offset;
+        self
+    }
+
+    pub(crate) fn with_ods_offset(mut self, offset: f64) -> Self {
+        self.ods_offset = offset;
+        self
+    }
+
+    // The rendered pixel rectangle as (xmin, xmax, ymin, ymax).
+    fn region(&self) -> (usize, usize, usize, usize) {
+        match self.crop {
+            Some(crop) => (crop.x, crop.x + crop.width, crop.y, crop.y + crop.height),
+            None => (0, self.image_width, 0, self.image_height),
+        }
+    }
+
+    // The region plus the pixels around it whose samples the filter spreads into it, so that a
+    // crop comes out the same as that part of the full image.
+    fn traced_region(&self) -> (usize, usize, usize, usize) {
+        let (xmin, xmax, ymin, ymax) = self.region();
+        let margin = self.reconstruction.margin();
+        (
+            xmin.saturating_sub(margin),
+            (xmax + margin).min(self.image_width),
+            ymin.saturating_sub(margin),
+            (ymax + margin).min(self.image_height),
+        )
+    }
+
+    // The output image as (x offset, y offset, width, height) within the camera frame.
+    fn output_window(&self) -> (usize, usize, usize, usize) {
+        match self.crop {
+            Some(crop) if !crop.full_frame => (crop.x, crop.y, crop.width, crop.height),
+            _ => (0, 0, self.image_width, self.image_height),
+        }
+    }
+
+    fn film_window(&self) -> FilmWindow {
+        let (x, y, _, _) = self.output_window();
+        FilmWindow {
+            x: x as u32,
+            y: y as u32,
+            film_width: self.image_width as u32,
+            film_height: self.image_height as u32,
+        }
+    }
+
+    // Only plain numbers go in, so the fingerprint stays the same across builds. Materials,
+    // textures, the background and the integrator are not covered, see `Checkpoint::version`.
+    fn fingerprint(&self, world: &Scene, aovs: &[Aov], version: u64) -> u64 {
+        let mut fingerprint = Fingerprint::default();
+        fingerprint.u64(version);
+        for size in [
+            self.image_width,
+            self.image_height,
+            self.samples_per_pixel,
+            self.max_depth,
+        ] {
+            fingerprint.u64(size as u64);
+        }
+        fingerprint.u64(self.crop.is_some() as u64);
+        if let Some(crop) = self.crop {
+            for value in [crop.x, crop.y, crop.width, crop.height] {
+                fingerprint.u64(value as u64);
+            }
+            fingerprint.u64(crop.full_frame as u64);
+        }
+        for v in [
+            self.pixel00_loc,
+            self.pixel_delta_u,
+            self.pixel_delta_v,
+            self.defocus_disk_u,
+            self.defocus_disk_v,
+            self.center,
+            self.focus_normal,
+        ] {
+            fingerprint.vec3(v);
+        }
+        let (projection, width) = match self.projection {
+            Projection::Perspective => (0, 0.0),
+            Projection::Orthographic { width } => (1, width),
+            Projection::Equirectangular => (2, 0.0),
+            Projection::Fisheye { fov } => (3, fov),
+        };
+        fingerprint.u64(projection);
+        fingerprint.f64(width);
+        let (curve, ramp) = match self.shutter_curve {
+            ShutterCurve::Box => (0, 0.0),
+            ShutterCurve::Triangle => (1, 0.0),
+            ShutterCurve::Trapezoid { ramp } => (2, ramp),
+        };
+        fingerprint.u64(curve);
+        for value in [
+            ramp,
+            self.ods_offset,
+            self.shutter_open,
+            self.shutter_close,
+            self.cats_eye,
+        ] {
+            fingerprint.f64(value);
+        }
+        match self.aperture {
+            Aperture::Circular => fingerprint.u64(0),
+            Aperture::Polygon { blades, rotation } => {
+                fingerprint.u64(1);
+                fingerprint.u64(blades as u64);
+                fingerprint.f64(rotation);
+            }
+            Aperture::Mask(ref mask) => {
+                fingerprint.u64(2);
+                fingerprint.u64(mask.digest());
+            }
+        }
+        fingerprint.u64(self.adaptive.is_some() as u64);
+        if let Some(adaptive) = self.adaptive {
+            fingerprint.u64(adaptive.min_samples as u64);
+            fingerprint.u64(adaptive.max_samples as u64);
+            fingerprint.f64(adaptive.threshold);
+        }
+        // Samplers are only known by what they return, so a few of their values stand in.
+        fingerprint.u64(self.seed);
+        fingerprint.u64(self.sampler.is_some() as u64);
+        if let Some(ref sampler) = self.sampler {
+            for index in 0..4 {
+                for dimension in 0..4 {
+                    fingerprint.f64(sampler.sample_1d(self.seed, (1, 2), index, dimension));
+                }
+            }
+        }
+        let (filter, parameters) = match self.reconstruction {
+            ReconstructionFilter::Box { radius } => (0, [radius, 0.0, 0.0]),
+            ReconstructionFilter::Gaussian { radius, sigma } => (1, [radius, sigma, 0.0]),
+            ReconstructionFilter::Mitchell { radius, b, c } => (2, [radius, b, c]),
+            ReconstructionFilter::Lanczos { radius } => (3, [radius, 0.0, 0.0]),
+            ReconstructionFilter::BlackmanHarris { radius } => (4, [radius, 0.0, 0.0]),
+        };
+        fingerprint.u64(filter);
+        for value in parameters {
+            fingerprint.f64(value);
+        }
+        for aov in aovs {
+            fingerprint.bytes(aov.name().as_bytes());
+            fingerprint.bytes(&[0]);
+        }
+        // The scene is summed up by where its objects are.
+        fingerprint.u64(world.objects().len() as u64);
+        for object in world.objects() {
+            let bbox = object.0.bbox();
+            for axis in [bbox.x, bbox.y, bbox.z] {
+                fingerprint.f64(axis.min);
+                fingerprint.f64(axis.max);
+            }
+        }
+        fingerprint.finish()
+    }
+
+    fn interruptible(&self) -> bool {
+        self.time_budget.is_some() || self.cancellation.is_some()
+    }
+
+    fn traced_aovs(&self) -> Vec<Aov> {
+        let mut aovs = self.aovs.clone();
+        if self.denoiser.is_some() {
+            aovs.extend([Aov::Depth, Aov::Normal, Aov::Albedo]);
+        }
+        if self.interruptible() {
+            aovs.push(Aov::SampleCount);
+        }
+        aovs.sort();
+        aovs.dedup();
+        aovs
+    }
+
+    // A ray through the film position (fx, fy), in pixels from the top left of the image.
+    fn get_ray(&self, fx: f64, fy: f64, samples: &mut SampleStream) -> Option<Ray> {
+        let lens = samples.next_2d();
+        let time = self.sample_time(samples.next_1d());
+        // Panoramic projections map image coordinates to directions around the camera centre.
+        let (s, t) = (fx / self.image_width as f64, fy / self.image_height as f64);
+        match self.projection {
+            Projection::Equirectangular => return Some(self.equirectangular_ray(s, t, time)),
+            Projection::Fisheye { fov } => {
+                let direction = self.fisheye_direction(s, t, fov)?;
+                return Some(Ray::time_dependent(self.center, direction, time));
+            }
+            _ => {}
+        }
         let pixel_sample = self.pixel00_loc
-            + ((x as f64 + offset.x) * self.pixel_delta_u)
-            + ((y as f64 + offset.y) * self.pixel_delta_v);
-        let ray_origin = if self.defocus_disk_u.near_zero() {
-            self.center
-        } else {
-            self.defocus_sample()
+            + ((fx - 0.5) * self.pixel_delta_u)
+            + ((fy - 0.5) * self.pixel_delta_v);
+        // Orthographic rays leave the lens plane straight behind their pixel.
+        let lens_center = match self.projection {
+            Projection::Orthographic { .. } => pixel_sample + self.focus_dist * self.w,
+            _ => self.center,
         };
-        Ray::time_dependent(ray_origin, pixel_sample - ray_origin, fastrand::f64())
+        if self.defocus_disk_u.near_zero() {
+            return Some(Ray::time_dependent(
+                lens_center,
+                pixel_sample - lens_center,
+                time,
+            ));
+        }
+        let focus = self.focus_point(lens_center, pixel_sample);
+        let ray_origin = lens_center + self.defocus_sample(s, t, lens)?;
+        Some(Ray::time_dependent(ray_origin, focus - ray_origin, time))
     }
 
-    fn sample_square_stratified(&self, si: usize, sj: usize) -> Vec3 {
-        Vec3::new(
-            ((si as f64) + fastrand::f64()) * self.recip_sqrt_spp - 0.5,
-            ((sj as f64) + fastrand::f64()) * self.recip_sqrt_spp - 0.5,
-            0.0,
-        )
+    // Where the chief ray through the pixel meets the plane of focus.
+    fn focus_point(&self, lens_center: Point3, pixel_sample: Point3) -> Point3 {
+        let direction = pixel_sample - lens_center;
+        let plane_point = self.center - self.focus_dist * self.w;
+        let t =
+            (plane_point - lens_center).dot(&self.focus_normal) / direction.dot(&self.focus_normal);
+        if t.is_finite() && t > 0.0 {
+            lens_center + t * direction
+        } else {
+            pixel_sample
+        }
+    }
+
+    fn sample_time(&self, u: f64) -> f64 {
+        let fraction = self.shutter_curve.sample(u);
+        self.shutter_open + fraction * (self.shutter_close - self.shutter_open)
     }
 
-    fn defocus_sample(&self) -> Vec3 {
-        let p = random_unit_disk();
-        self.center + p.x * self.defocus_disk_u + p.y * self.defocus_disk_v
+    fn equirectangular_ray(&self, s: f64, t: f64, time: f64) -> Ray {
+        let longitude = (2.0 * s - 1.0) * f64::consts::PI;
+        let latitude = (0.5 - t) * f64::consts::PI;
+        let direction = latitude.cos() * (longitude.sin() * self.u - longitude.cos() * self.w)
+            + latitude.sin() * self.v;
+        // Omni-directional stereo: each eye sits on a circle, tangent to the viewing direction.
+        let eye = self.ods_offset * (longitude.cos() * self.u + longitude.sin() * self.w);
+        Ray::time_dependent(self.center + eye, direction, time)
     }
 
-    fn render_block(&self, block: &mut ImageBlock, world: &BVHNode, lights: &Scene) {
-        let pixel_samples_scale = 1.0 / (self.sqrt_spp * self.sqrt_spp) as f64;
+    fn fisheye_direction(&self, s: f64, t: f64, fov: f64) -> Option<Vec3> {
+        // Equidistant mapping onto the circle inscribed in the image.
+        let scale = self.image_width.min(self.image_height) as f64;
+        let nx = (2.0 * s - 1.0) * self.image_width as f64 / scale;
+        let ny = (1.0 - 2.0 * t) * self.image_height as f64 / scale;
+        let r = (nx * nx + ny * ny).sqrt();
+        if r > 1.0 {
+            return None;
+        }
+        let theta = r * fov.to_radians() / 2.0;
+        let phi = ny.atan2(nx);
+        Some(theta.sin() * (phi.cos() * self.u + phi.sin() * self.v) - theta.cos() * self.w)
+    }
+
+    fn defocus_sample(&self, s: f64, t: f64, u: (f64, f64)) -> Option<Vec3> {
+        let p = self.aperture.sample(u);
+        // Cat's eye: towards the image edges the lens barrel clips the aperture.
+        if self.cats_eye > 0.0 {
+            let barrel = self.cats_eye * Vec3::new(2.0 * s - 1.0, 1.0 - 2.0 * t, 0.0);
+            if (p - barrel).dot(&(p - barrel)) > 1.0 {
+                return None;
+            }
+        }
+        Some(p.x * self.defocus_disk_u + p.y * self.defocus_disk_v)
+    }
+
+    fn render_block(&self, block: &mut ImageBlock, ctx: &RenderContext, budget: usize) -> usize {
+        let default = StratifiedSampler::new(match self.adaptive {
+            // Strata are cycled through, so the grid is sized for the minimal sample count.
+            Some(adaptive) => adaptive.min_samples,
+            None => self.samples_per_pixel,
+        });
+        let sampler = self.sampler.as_deref().unwrap_or(&default);
+        let mut taken = 0;
         for y in block.ymin..block.ymax {
             for x in block.xmin..block.xmax {
-                let mut c = Colour::new(0.0, 0.0, 0.0);
-                for sj in 0..self.sqrt_spp {
-                    for si in 0..self.sqrt_spp {
-                        let r = self.get_ray(x, y, si, sj);
-                        c += ray_colour(r, world, lights, self.max_depth, self.background.as_ref());
-                    }
-                }
-                c = pixel_samples_scale * c;
-                block.write(x, y, c);
+                taken += self.render_pixel(x, y, block, ctx, sampler, budget);
             }
         }
+        taken
+    }
+
+    fn render_pixel(
+        &self,
+        x: usize,
+        y: usize,
+        block: &mut ImageBlock,
+        ctx: &RenderContext,
+        sampler: &dyn Sampler,
+        budget: usize,
+    ) -> usize {
+        let depth = |p: Point3| match self.projection {
+            Projection::Equirectangular | Projection::Fisheye { .. } => (p - self.center).length(),
+            _ => (p - self.center).dot(&-self.w),
+        };
+        let mut taken = 0;
+        while taken < budget
+            && self.needs_sample(&block.pixel_mut(x, y).estimate)
+            && !ctx.interrupted()
+        {
+            let index = block.pixel_mut(x, y).estimate.count();
+            let mut samples = SampleStream::new(sampler, self.seed, x, y, index);
+            // The camera always takes the first dimensions: film position, lens, then time.
+            let (dx, dy) = samples.next_2d();
+            let (fx, fy) = (x as f64 + dx, y as f64 + dy);
+            let ray = self.get_ray(fx, fy, &mut samples);
+            let mut surface = None;
+            let radiance = match ray {
+                Some(ray) => {
+                    statistics::add(Counter::Paths, 1);
+                    self.integrator
+                        .radiance(ray, &ctx.scene, &mut samples, &mut surface)
+                }
+                // Outside the image circle of a fisheye lens, or vignetted by the lens barrel.
+                None => Radiance::default(),
+            };
+            block.splat(fx, fy, radiance.total(), &self.reconstruction);
+            let pixel = block.pixel_mut(x, y);
+            pixel.estimate.add(radiance.total().luminance());
+            pixel.aovs.add(ctx.aovs, &radiance, surface.as_ref(), depth);
+            taken += 1;
+        }
+        taken
+    }
+
+    fn needs_sample(&self, estimate: &RunningVariance) -> bool {
+        match self.adaptive {
+            Some(adaptive) => adaptive.needs_sample(estimate),
+            None => estimate.count() < self.samples_per_pixel,
+        }
     }
 
-    pub fn render_with_filter<P, F>(&self, world: &mut Scene, filter: F, p: &mut P) -> RgbImage
+    fn trace_pass(
+        &self,
+        blocks: &mut [ImageBlock],
+        ctx: &RenderContext,
+        budget: usize,
+        statistics: &mut RenderStatistics,
+    ) -> usize {
+        let finished = AtomicUsize::new(0);
+        let total = blocks.len();
+        let (taken, counters) = blocks
+            .par_iter_mut()
+            .map(|block| {
+                let start = Instant::now();
+                statistics::take();
+                let taken = self.render_block(block, ctx, budget);
+                let counters = statistics::take();
+                self.progress.notify(&ProgressEvent::BlockFinished {
+                    xmin: block.xmin,
+                    xmax: block.xmax,
+                    ymin: block.ymin,
+                    ymax: block.ymax,
+                    elapsed: start.elapsed(),
+                    finished: finished.fetch_add(1, Ordering::Relaxed) + 1,
+                    total,
+                });
+                (taken, counters)
+            })
+            .reduce(
+                || (0, Counters::default()),
+                |(a, mut counters), (b, other)| {
+                    counters += other;
+                    (a + b, counters)
+                },
+            );
+        *statistics += counters;
+        taken
+    }
+
+    pub fn render_progressive_with_filter<F, C>(
+        &self,
+        world: &mut Scene,
+        filter: F,
+        samples_per_pass: usize,
+        callback: C,
+    ) -> Result<Frame, RenderError>
+    where
+        F: RenderFilter,
+        C: FnMut(usize, &Rgb32FImage) -> ControlFlow<()>,
+    {
+        let mut callback = callback;
+        let scene = self.prepare_with_filter(world, filter);
+        self.trace_scene(world, &scene, samples_per_pass, Some(&mut callback))
+    }
+
+    pub(crate) fn prepare(&self, world: &Scene) -> PreparedScene {
+        self.prepare_with_filter(world, TrivialFilter)
+    }
+
+    fn prepare_with_filter<F>(&self, world: &Scene, filter: F) -> PreparedScene
     where
-        P: Write + Sync + Send,
         F: RenderFilter,
     {
-        let p = Arc::new(Mutex::new(p));
-        writeln!(p.lock().unwrap(), "Collecting light sources...").unwrap();
+        let start = Instant::now();
+        let mut timings = Vec::new();
+        let progress = self.progress.as_ref();
+        let mut phase_start = Instant::now();
+        progress.notify(&ProgressEvent::PhaseStarted(Phase::CollectingLights));
         let lights = Scene::with_objects(world.lights());
-        writeln!(p.lock().unwrap(), "Building render node hierarchy...").unwrap();
+        timings.push((Phase::CollectingLights, phase_start.elapsed()));
+        phase_start = Instant::now();
+        progress.notify(&ProgressEvent::PhaseStarted(Phase::BuildingHierarchy));
         let mut raw_objects = world.objects().iter().map(|o| Arc::clone(o)).collect();
         filter.filter(self, &mut raw_objects);
+        let aovs = self.traced_aovs();
+        if aovs.contains(&Aov::ObjectId) || aovs.contains(&Aov::MaterialId) {
+            raw_objects = Tagged::tag(raw_objects);
+        }
+        let primitives = raw_objects.len();
         let bvh = BVHNode::new(&mut raw_objects);
-        let mut blocks = self.image_blocks();
-        writeln!(
-            p.lock().unwrap(),
-            "Split target image into {} blocks...",
-            blocks.len()
-        )
-        .unwrap();
-        let done = AtomicUsize::new(0);
-        let total = blocks.len();
-        blocks.par_iter_mut().for_each(|block| {
-            self.render_block(block, &bvh, &lights);
-            done.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-            write!(
-                p.lock().unwrap(),
-                "\r{:.2}% done...",
-                done.load(std::sync::atomic::Ordering::Relaxed) as f64 / total as f64 * 100.0
-            )
-            .unwrap();
+        timings.push((Phase::BuildingHierarchy, phase_start.elapsed()));
+        progress.notify(&ProgressEvent::HierarchyBuilt {
+            nodes: bvh.nodes(),
+            primitives,
         });
-        writeln!(
-            p.lock().unwrap(),
-            "\rRay tracing done.                             "
-        )
-        .unwrap();
-        self.assemble_image(&blocks)
+        PreparedScene {
+            lights,
+            bvh,
+            timings,
+            build_time: start.elapsed(),
+        }
+    }
+
+    pub(crate) fn render_prepared(
+        &self,
+        world: &Scene,
+        scene: &PreparedScene,
+    ) -> Result<Frame, RenderError> {
+        // Refine the whole image evenly under a time budget, so running out of time leaves no
+        // blank blocks, and when checkpointing, so there are passes to save between.
+        let samples_per_pass = if self.time_budget.is_some() || self.checkpoint.is_some() {
+            1
+        } else {
+            usize::MAX
+        };
+        self.trace_scene(world, scene, samples_per_pass, None)
+    }
+
+    // Previews are only assembled for a callback, as they run the whole post-process stack.
+    fn trace_scene(
+        &self,
+        world: &Scene,
+        scene: &PreparedScene,
+        samples_per_pass: usize,
+        mut callback: Option<&mut PassCallback>,
+    ) -> Result<Frame, RenderError> {
+        // A crop that misses the image is clamped to nothing, which is almost certainly a mistake.
+        if self
+            .crop
+            .is_some_and(|crop| crop.width == 0 || crop.height == 0)
+        {
+            return Err(RenderError::EmptyCrop);
+        }
+        if let Some(adaptive) = self.adaptive {
+            adaptive.validate()?;
+        }
+        let start = Instant::now();
+        let mut statistics = RenderStatistics {
+            timings: scene.timings.clone(),
+            ..RenderStatistics::default()
+        };
+        let progress = self.progress.as_ref();
+        let mut phase_start;
+        let aovs = self.traced_aovs();
+        let mut blocks = self.image_blocks(aovs.len());
+        progress.notify(&ProgressEvent::ImageSplit {
+            blocks: blocks.len(),
+        });
+        let ctx = RenderContext {
+            scene: TraceContext {
+                world: &scene.bvh,
+                lights: &scene.lights,
+                background: self.background.as_ref(),
+                max_depth: self.max_depth,
+            },
+            aovs: &aovs,
+            deadline: self.time_budget.map(|budget| Instant::now() + budget),
+            cancellation: self.cancellation.as_ref(),
+        };
+        let checkpoint = self.checkpoint.as_ref().map(|checkpoint| {
+            let fingerprint = self.fingerprint(world, &aovs, checkpoint.version);
+            CheckpointState::new(checkpoint, fingerprint, blocks.len())
+        });
+        if let Some(ref checkpoint) = checkpoint {
+            let snapshots = checkpoint.load()?;
+            for (block, snapshot) in blocks.iter_mut().zip(snapshots) {
+                if let Some(snapshot) = snapshot {
+                    let mut decoder = Decoder::new(&snapshot, checkpoint.path());
+                    block.restore(&mut decoder, aovs.len())?;
+                }
+            }
+            progress.notify(&ProgressEvent::CheckpointOpened {
+                path: checkpoint.file(),
+            });
+        }
+        let (mut complete, mut cancelled) = (true, false);
+        for pass in 1.. {
+            phase_start = Instant::now();
+            progress.notify(&ProgressEvent::PhaseStarted(Phase::Tracing { pass }));
+            let taken = self.trace_pass(&mut blocks, &ctx, samples_per_pass, &mut statistics);
+            statistics
+                .timings
+                .push((Phase::Tracing { pass }, phase_start.elapsed()));
+            progress.notify(&ProgressEvent::PassFinished {
+                pass,
+                samples: taken,
+            });
+            if ctx.interrupted() {
+                cancelled = ctx.cancelled();
+                complete = false;
+                break;
+            }
+            if taken == 0 {
+                break;
+            }
+            match callback.as_mut() {
+                Some(callback) => {
+                    let mut preview = self.assemble_image(&blocks);
+                    for effect in &self.post_processes {
+                        effect.apply(&mut preview, &self.film_window());
+                    }
+                    if callback(pass, &preview).is_break() {
+                        break;
+                    }
+                }
+                None if samples_per_pass == usize::MAX => break,
+                None => {}
+            }
+            if let Some(checkpoint) = checkpoint.as_ref().filter(|checkpoint| checkpoint.due()) {
+                Self::save_checkpoint(checkpoint, &blocks)?;
+            }
+        }
+        progress.notify(&ProgressEvent::TracingFinished);
+        if cancelled {
+            progress.notify(&ProgressEvent::Cancelled);
+        } else if !complete {
+            progress.notify(&ProgressEvent::TimeBudgetExhausted);
+        }
+        // Whatever was traced since the last save is written out once, when tracing stops.
+        if let Some(ref checkpoint) = checkpoint {
+            Self::save_checkpoint(checkpoint, &blocks)?;
+        }
+        let mut frame = self.assemble_frame(&blocks, &aovs, complete);
+        // A cancelled render stops as soon as it can, so the frame is handed back as traced.
+        if let Some(denoiser) = self.denoiser.filter(|_| !cancelled) {
+            phase_start = Instant::now();
+            progress.notify(&ProgressEvent::PhaseStarted(Phase::Denoising));
+            let features = Features {
+                normal: &frame.aovs[&Aov::Normal],
+                albedo: &frame.aovs[&Aov::Albedo],
+                depth: &frame.aovs[&Aov::Depth],
+            };
+            frame.image = denoiser.denoise(&frame.image, &features);
+            statistics
+                .timings
+                .push((Phase::Denoising, phase_start.elapsed()));
+        }
+        if !self.post_processes.is_empty() && !cancelled {
+            phase_start = Instant::now();
+            progress.notify(&ProgressEvent::PhaseStarted(Phase::PostProcessing));
+            for effect in &self.post_processes {
+                effect.apply(&mut frame.image, &self.film_window());
+            }
+            statistics
+                .timings
+                .push((Phase::PostProcessing, phase_start.elapsed()));
+        }
+        // Renders that can be cut short always report how many samples each pixel received.
+        let keep_counts = self.interruptible();
+        frame
+            .aovs
+            .retain(|aov, _| self.aovs.contains(aov) || (keep_counts && *aov == Aov::SampleCount));
+        statistics.total_time = scene.build_time + start.elapsed();
+        if cancelled {
+            frame.statistics = statistics;
+            return Err(RenderError::Cancelled(Box::new(frame)));
+        }
+        progress.notify(&ProgressEvent::RenderFinished {
+            statistics: &statistics,
+        });
+        frame.statistics = statistics;
+        Ok(frame)
+    }
+
+    // Blocks are only snapshotted to be saved, between passes or once tracing stops.
+    fn save_checkpoint(checkpoint: &CheckpointState, blocks: &[ImageBlock]) -> std::io::Result<()> {
+        for (index, block) in blocks.iter().enumerate() {
+            checkpoint.record(index, block.snapshot());
+        }
+        checkpoint.save()
+    }
+
+    pub fn render_progressive<C>(
+        &self,
+        world: &mut Scene,
+        samples_per_pass: usize,
+        callback: C,
+    ) -> Result<Frame, RenderError>
+    where
+        C: FnMut(usize, &Rgb32FImage) -> ControlFlow<()>,
+    {
+        self.render_progressive_with_filter(world, TrivialFilter, samples_per_pass, callback)
+    }
+
+    pub fn render_frame_with_filter<F>(
+        &self,
+        world: &mut Scene,
+        filter: F,
+    ) -> Result<Frame, RenderError>
+    where
+        F: RenderFilter,
+    {
+        let scene = self.prepare_with_filter(world, filter);
+        self.render_prepared(world, &scene)
     }
 
-    pub fn render<P>(&self, world: &mut Scene, p: &mut P) -> RgbImage
+    pub fn render_frame(&self, world: &mut Scene) -> Result<Frame, RenderError> {
+        self.render_frame_with_filter(world, TrivialFilter)
+    }
+
+    pub fn render_hdr_with_filter<F>(
+        &self,
+        world: &mut Scene,
+        filter: F,
+    ) -> Result<Rgb32FImage, RenderError>
     where
-        P: Write + Sync + Send,
+        F: RenderFilter,
     {
-        self.render_with_filter(world, TrivialFilter, p)
+        Ok(self.render_frame_with_filter(world, filter)?.image)
     }
 
-    pub fn render_to_file<F, P>(
+    pub fn render_with_filter<F>(
         &self,
         world: &mut Scene,
-        path: F,
-        p: &mut P,
-    ) -> Result<(), RenderError>
+        filter: F,
+    ) -> Result<RgbImage, RenderError>
     where
-        F: AsRef<Path> + Display,
-        P: Write + Sync + Send,
+        F: RenderFilter,
+    {
+        Ok(self.to_ldr(&self.render_hdr_with_filter(world, filter)?))
+    }
+
+    pub fn render_hdr(&self, world: &mut Scene) -> Result<Rgb32FImage, RenderError> {
+        self.render_hdr_with_filter(world, TrivialFilter)
+    }
+
+    pub fn render(&self, world: &mut Scene) -> Result<RgbImage, RenderError> {
+        self.render_with_filter(world, TrivialFilter)
+    }
+
+    pub fn render_to_file<F>(&self, world: &mut Scene, path: F) -> Result<(), RenderError>
+    where
+        F: AsRef<Path>,
     {
-        let buffer = self.render(world, p);
-        writeln!(p, "Saving image to {path}...").unwrap();
-        buffer.save(path).map_err(|e| e.into())
+        let buffer = self.render_hdr(world)?;
+        self.save(&buffer, path)
+    }
+
+    pub(crate) fn save(
+        &self,
+        buffer: &Rgb32FImage,
+        path: impl AsRef<Path>,
+    ) -> Result<(), RenderError> {
+        self.progress.notify(&ProgressEvent::Saving {
+            path: path.as_ref(),
+        });
+        if is_hdr_path(&path) {
+            save_hdr(buffer, path)
+        } else {
+            self.to_ldr(buffer).save(path).map_err(|e| e.into())
+        }
     }
 
-    fn image_blocks(&self) -> Vec<ImageBlock> {
+    pub fn to_ldr(&self, buffer: &Rgb32FImage) -> RgbImage {
+        let mut ldr = Rgb32FImage::new(buffer.width(), buffer.height());
+        for (target, pixel) in ldr.pixels_mut().zip(buffer.pixels()) {
+            *target = self.display.encode(Colour::from(pixel));
+        }
+        ldr.convert()
+    }
+
+    fn image_blocks(&self, n_aovs: usize) -> Vec<ImageBlock> {
+        let (xmin, xmax, ymin, ymax) = self.traced_region();
         let mut blocks = Vec::new();
-        for i in 0..self.image_height / Self::BLOCK_SIZE + 1 {
-            let ymin = i * Self::BLOCK_SIZE;
-            let ymax = ((i + 1) * Self::BLOCK_SIZE).min(self.image_height);
-            for j in 0..self.image_width / Self::BLOCK_SIZE + 1 {
-                let xmin = j * Self::BLOCK_SIZE;
-                let xmax = ((j + 1) * Self::BLOCK_SIZE).min(self.image_width);
-                blocks.push(ImageBlock::new(xmin, xmax, ymin, ymax));
+        for y in (ymin..ymax).step_by(Self::BLOCK_SIZE) {
+            for x in (xmin..xmax).step_by(Self::BLOCK_SIZE) {
+                blocks.push(ImageBlock::new(
+                    x,
+                    (x + Self::BLOCK_SIZE).min(xmax),
+                    y,
+                    (y + Self::BLOCK_SIZE).min(ymax),
+                    n_aovs,
+                    self.reconstruction.margin(),
+                ));
             }
         }
         blocks
     }
 
-    fn assemble_image(&self, blocks: &[ImageBlock]) -> RgbImage {
-        let mut buffer = Rgb32FImage::new(self.image_width as u32, self.image_height as u32);
+    fn assemble_image(&self, blocks: &[ImageBlock]) -> Rgb32FImage {
+        let (x0, y0, width, height) = self.output_window();
+        let (xmin, xmax, ymin, ymax) = self.region();
+        // Film tiles overlap by the filter margin, so neighbouring blocks add up here. Splats
+        // that land outside the crop are dropped, so a full frame crop stays black around it.
+        let mut film = vec![FilmPixel::default(); width * height];
         for block in blocks {
-            for (k, colour) in block.buffer.iter().enumerate() {
-                let x = k % (block.xmax - block.xmin) + block.xmin;
-                let y = k / (block.xmax - block.xmin) + block.ymin;
-                *buffer.get_pixel_mut(x as u32, y as u32) = colour.into();
+            for (x, y, splat) in block.film() {
+                if (xmin..xmax).contains(&x) && (ymin..ymax).contains(&y) {
+                    film[(x - x0) + (y - y0) * width] += *splat;
+                }
             }
         }
-        buffer.convert()
+        let mut image = Rgb32FImage::new(width as u32, height as u32);
+        for (pixel, splat) in image.pixels_mut().zip(film) {
+            *pixel = self.display.expose(splat.colour()).linear();
+        }
+        image
     }
-}
 
-fn ray_colour(
-    ray: Ray,
-    world: &BVHNode,
-    lights: &Scene,
-    depth: usize,
-    background: &dyn Textured,
-) -> Colour {
-    if depth == 0 {
-        return Colour::BLACK;
-    }
-    if let Some(rec) = world.hit(&ray, Interval::new(0.001, f64::INFINITY)) {
-        let colour_from_emission = rec.material.emit(&rec, rec.u, rec.v, rec.p);
-        let light_pdf = HittablePDF::new(lights, rec.p);
-        if let Some(scatter) = rec.material.scatter(ray, &rec) {
-            match scatter.scattered {
-                ScatterResult::SpecularRay(specular_ray) => {
-                    ray_colour(specular_ray, world, lights, depth - 1, background)
-                        .attenuate(&scatter.attenuation)
-                }
-                ScatterResult::PDF(pdf) => {
-                    let mixture = if !lights.objects().is_empty() {
-                        MixturePDF::new(&light_pdf, pdf.as_ref())
-                    } else {
-                        MixturePDF::new(pdf.as_ref(), pdf.as_ref())
-                    };
-                    let scattered = Ray::time_dependent(rec.p, mixture.generate(), ray.time);
-                    let pdf_value = mixture.value(&scattered.direction);
-                    let scattering_pdf = rec.material.scattering_pdf(ray, &rec, scattered);
-                    let colour_from_scatter = scattering_pdf / pdf_value
-                        * ray_colour(scattered, world, lights, depth - 1, background)
-                            .attenuate(&scatter.attenuation);
-                    colour_from_emission + colour_from_scatter
+    fn assemble_frame(&self, blocks: &[ImageBlock], aovs: &[Aov], complete: bool) -> Frame {
+        let (x0, y0, width, height) = self.output_window();
+        let new_buffer = || Rgb32FImage::new(width as u32, height as u32);
+        let mut buffers: Vec<_> = aovs.iter().map(|_| new_buffer()).collect();
+        let (xmin, xmax, ymin, ymax) = self.region();
+        for block in blocks {
+            // Pixels only traced for the filter margin around a crop are left out.
+            let pixels = block
+                .pixels()
+                .filter(|&(x, y, _)| (xmin..xmax).contains(&x) && (ymin..ymax).contains(&y));
+            for (x, y, pixel) in pixels {
+                for (buffer, value) in buffers.iter_mut().zip(pixel.aovs.resolve(aovs)) {
+                    *buffer.get_pixel_mut((x - x0) as u32, (y - y0) as u32) = value.linear();
                 }
             }
-        } else {
-            colour_from_emission
         }
-    } else {
-        let (u, v) = sphere_uv(ray.direction.normalize());
-        background.value(u, v, Vec3::ZERO)
+        Frame {
+            image: self.assemble_image(blocks),
+            aovs: aovs.iter().copied().zip(buffers).collect(),
+            complete,
+            statistics: RenderStatistics::default(),
+        }
     }
 }
 
+pub(crate) struct PreparedScene {
+    lights: Scene,
+    bvh: BVHNode<'static>,
+    timings: Vec<(Phase, Duration)>,
+    build_time: Duration,
+}
+
+#[derive(Debug)]
+struct Tagged {
+    object: Arc<dyn Hittable>,
+    id: usize,
+    materials: Arc<HashMap<usize, usize>>,
+}
+
+impl Tagged {
+    fn tag(objects: Vec<Arc<dyn Hittable>>) -> Vec<Arc<dyn Hittable>> {
+        // Materials are numbered in the order the scene first uses them, keyed by the shared
+        // allocation so that every object with the same material gets the same id.
+        let mut materials = HashMap::new();
+        for material in objects.iter().flat_map(|object| object.materials()) {
+            let next = materials.len() + 1;
+            materials.entry(material_key(&**material)).or_insert(next);
+        }
+        let materials = Arc::new(materials);
+        objects
+            .into_iter()
+            .enumerate()
+            .map(|(k, object)| {
+                Arc::new(Self {
+                    object,
+                    id: k + 1,
+                    materials: Arc::clone(&materials),
+                }) as Arc<dyn Hittable>
+            })
+            .collect()
+    }
+}
+
+fn material_key(material: &dyn Scatter) -> usize {
+    material as *const dyn Scatter as *const () as usize
+}
+
+impl Hittable for Tagged {
+    fn hit(&self, ray: &Ray, range: Interval) -> Option<HitRecord<'_>> {
+        let mut hit = self.object.hit(ray, range);
+        if let Some(ref mut rec) = hit {
+            rec.object_id = self.id;
+            rec.material_id = self
+                .materials
+                .get(&material_key(rec.material))
+                .copied()
+                .unwrap_or_default();
+        }
+        hit
+    }
+    fn bbox(&self) -> AaBb {
+        self.object.bbox()
+    }
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, time: f64) -> f64 {
+        self.object.pdf_value(origin, direction, time)
+    }
+    fn random(&self, origin: &Point3, time: f64, samples: &mut SampleStream) -> Vec3 {
+        self.object.random(origin, time, samples)
+    }
+    fn lights(&self) -> Collection {
+        self.object.lights()
+    }
+    fn materials(&self) -> Vec<Material> {
+        self.object.materials()
+    }
+}
+
+struct PixelState {
+    estimate: RunningVariance,
+    aovs: AovPixel,
+}
+
+impl PixelState {
+    fn new(n_aovs: usize) -> Self {
+        Self {
+            estimate: RunningVariance::default(),
+            aovs: AovPixel::new(n_aovs),
+        }
+    }
+
+    fn encode(&self, encoder: &mut Encoder) {
+        self.estimate.encode(encoder);
+        self.aovs.encode(encoder);
+    }
+
+    fn decode(decoder: &mut Decoder, n_aovs: usize) -> Result<Self, RenderError> {
+        Ok(Self {
+            estimate: RunningVariance::decode(decoder)?,
+            aovs: AovPixel::decode(decoder, n_aovs)?,
+        })
+    }
+}
+
+// Filter-weighted radiance gathered at one pixel, alongside the plain average of the samples
+// inside it.
+#[derive(Debug, Default, Clone, Copy)]
+struct FilmPixel {
+    sum: Colour,
+    weight: f64,
+    box_sum: Colour,
+    box_count: u64,
+}
+
+impl FilmPixel {
+    const MIN_WEIGHT: f64 = 1e-3;
+
+    fn colour(&self) -> Colour {
+        // Negative lobes can cancel out the weights, which would blow the division up.
+        let colour = if self.weight > Self::MIN_WEIGHT {
+            self.sum / self.weight
+        } else if self.box_count > 0 {
+            self.box_sum / self.box_count as f64
+        } else {
+            Colour::BLACK
+        };
+        Colour::from_components(colour.components().map(|c| c.max(0.0)))
+    }
+}
+
+impl AddAssign for FilmPixel {
+    fn add_assign(&mut self, rhs: Self) {
+        self.sum += rhs.sum;
+        self.weight += rhs.weight;
+        self.box_sum += rhs.box_sum;
+        self.box_count += rhs.box_count;
+    }
+}
+
+// A block owns its pixels plus a film tile reaching `margin` pixels beyond them, so samples can
+// be splatted into neighbouring blocks without sharing state between threads.
 struct ImageBlock {
     xmin: usize,
     xmax: usize,
     ymin: usize,
     ymax: usize,
-    buffer: Vec<Colour>,
+    margin: usize,
+    pixels: Vec<PixelState>,
+    film: Vec<FilmPixel>,
 }
 
 impl ImageBlock {
-    fn write(&mut self, x: usize, y: usize, c: Colour) {
-        self.buffer[(x - self.xmin) + (y - self.ymin) * (self.xmax - self.xmin)] = c;
+    fn splat(&mut self, fx: f64, fy: f64, colour: Colour, filter: &ReconstructionFilter) {
+        let radius = filter.radius();
+        let stride = self.xmax - self.xmin + 2 * self.margin;
+        let (left, top) = (
+            self.xmin as f64 - self.margin as f64,
+            self.ymin as f64 - self.margin as f64,
+        );
+        let rows = self.film.len() / stride;
+        // The pixels whose centres lie within (-radius, radius] of the sample, which matches
+        // the half-open support of the filter on the sample's offset from each centre.
+        let reach = |f: f64, start: f64, end: usize| {
+            let first = (f - radius - start - 0.5).floor() + 1.0;
+            let last = (f + radius - start - 0.5).floor() + 1.0;
+            (first.max(0.0) as usize)..(last.max(0.0) as usize).min(end)
+        };
+        let (x_range, y_range) = (reach(fx, left, stride), reach(fy, top, rows));
+        for j in y_range {
+            for i in x_range.clone() {
+                let (dx, dy) = (fx - (left + i as f64 + 0.5), fy - (top + j as f64 + 0.5));
+                let weight = filter.weight(dx, dy);
+                let inside = (-0.5..0.5).contains(&dx) && (-0.5..0.5).contains(&dy);
+                if weight != 0.0 || inside {
+                    self.film[i + j * stride] += FilmPixel {
+                        sum: weight * colour,
+                        weight,
+                        box_sum: if inside { colour } else { Colour::BLACK },
+                        box_count: inside as u64,
+                    };
+                }
+            }
+        }
+    }
+    fn film(&self) -> impl Iterator<Item = (usize, usize, &FilmPixel)> {
+        let stride = self.xmax - self.xmin + 2 * self.margin;
+        self.film.iter().enumerate().filter_map(move |(k, splat)| {
+            // Margins hanging off the top or left of the image have no pixels to land on.
+            let x = (k % stride + self.xmin).checked_sub(self.margin)?;
+            let y = (k / stride + self.ymin).checked_sub(self.margin)?;
+            Some((x, y, splat))
+        })
+    }
+    fn pixel_mut(&mut self, x: usize, y: usize) -> &mut PixelState {
+        &mut self.pixels[(x - self.xmin) + (y - self.ymin) * (self.xmax - self.xmin)]
+    }
+    fn pixels(&self) -> impl Iterator<Item = (usize, usize, &PixelState)> {
+        self.pixels.iter().enumerate().map(|(k, pixel)| {
+            let x = k % (self.xmax - self.xmin) + self.xmin;
+            let y = k / (self.xmax - self.xmin) + self.ymin;
+            (x, y, pixel)
+        })
+    }
+    fn snapshot(&self) -> Vec<u8> {
+        let mut encoder = Encoder::default();
+        for pixel in self.pixels.iter() {
+            pixel.encode(&mut encoder);
+        }
+        for splat in self.film.iter() {
+            encoder.colour(splat.sum);
+            encoder.f64(splat.weight);
+            encoder.colour(splat.box_sum);
+            encoder.u64(splat.box_count);
+        }
+        encoder.finish()
+    }
+    fn restore(&mut self, decoder: &mut Decoder, n_aovs: usize) -> Result<(), RenderError> {
+        for pixel in self.pixels.iter_mut() {
+            *pixel = PixelState::decode(decoder, n_aovs)?;
+        }
+        for splat in self.film.iter_mut() {
+            splat.sum = decoder.colour()?;
+            splat.weight = decoder.f64()?;
+            splat.box_sum = decoder.colour()?;
+            splat.box_count = decoder.u64()?;
+        }
+        Ok(())
     }
-    fn new(xmin: usize, xmax: usize, ymin: usize, ymax: usize) -> Self {
+    fn new(
+        xmin: usize,
+        xmax: usize,
+        ymin: usize,
+        ymax: usize,
+        n_aovs: usize,
+        margin: usize,
+    ) -> Self {
         Self {
             xmin,
             xmax,
             ymin,
             ymax,
-            buffer: vec![Colour::BLACK; (xmax - xmin) * (ymax - ymin)],
+            margin,
+            pixels: (0..(xmax - xmin) * (ymax - ymin))
+                .map(|_| PixelState::new(n_aovs))
+                .collect(),
+            film: vec![
+                FilmPixel::default();
+                (xmax - xmin + 2 * margin) * (ymax - ymin + 2 * margin)
+            ],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::{Dielectric, Lambertian, Metal};
+    use crate::objects::Sphere;
+    use crate::texture::SolidColour;
+
+    // Puts every sample on the top left corner of its pixel.
+    #[derive(Debug)]
+    struct CornerSampler;
+
+    impl Sampler for CornerSampler {
+        fn sample_1d(&self, _: u64, _: (usize, usize), _: usize, _: usize) -> f64 {
+            0.0
+        }
+    }
+
+    fn spheres() -> Scene {
+        let mut world = Scene::new();
+        world.add(Sphere::new(
+            Point3::new(0.0, -100.5, -1.0),
+            100.0,
+            Lambertian::new(Colour::new(0.8, 0.8, 0.0)),
+        ));
+        world.add(Sphere::new(
+            Point3::new(-0.5, 0.0, -1.0),
+            0.5,
+            Dielectric::new(1.5),
+        ));
+        world.add(Sphere::new(
+            Point3::new(0.5, 0.0, -1.0),
+            0.5,
+            Metal::new(Colour::new(0.8, 0.6, 0.2), 0.3),
+        ));
+        world
+    }
+
+    fn render_on(threads: usize, renderer: &Renderer, world: &mut Scene) -> Rgb32FImage {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap();
+        pool.install(|| renderer.render_frame(world).unwrap().image)
+    }
+
+    #[test]
+    fn renders_only_depend_on_the_seed() {
+        let mut world = spheres();
+        let cam = Camera {
+            image_width: 48,
+            ..Camera::default()
+        };
+        let renderer = |seed| {
+            cam.renderer(4, 8)
+                .with_progress(|_: &ProgressEvent| {})
+                .with_seed(seed)
+        };
+        let single = render_on(1, &renderer(7), &mut world);
+        assert_eq!(render_on(4, &renderer(7), &mut world), single);
+        assert_ne!(render_on(1, &renderer(8), &mut world), single);
+    }
+
+    #[test]
+    fn crops_match_the_full_render() {
+        let mut world = spheres();
+        let cam = Camera {
+            image_width: 48,
+            ..Camera::default()
+        };
+        let filters = [
+            ReconstructionFilter::default(),
+            ReconstructionFilter::Mitchell {
+                radius: 2.0,
+                b: 1.0 / 3.0,
+                c: 1.0 / 3.0,
+            },
+        ];
+        for filter in filters {
+            let renderer = || {
+                cam.renderer(4, 8)
+                    .with_progress(|_: &ProgressEvent| {})
+                    .with_reconstruction_filter(filter)
+                    .with_aovs(&[Aov::Normal])
+            };
+            let full = renderer().render_frame(&mut world).unwrap();
+            let crop = renderer()
+                .with_crop(Crop::new(10, 5, 20, 8))
+                .render_frame(&mut world)
+                .unwrap();
+            let window =
+                |image: &Rgb32FImage| image::imageops::crop_imm(image, 10, 5, 20, 8).to_image();
+            assert_eq!(crop.image, window(&full.image), "{filter:?}");
+            assert_eq!(
+                crop.aovs[&Aov::Normal],
+                window(&full.aovs[&Aov::Normal]),
+                "{filter:?}"
+            );
+            // In a full frame crop, the same pixels sit in place with black around them.
+            let framed = renderer()
+                .with_crop(Crop::new(10, 5, 20, 8).in_full_frame())
+                .render_frame(&mut world)
+                .unwrap();
+            assert_eq!(window(&framed.image), crop.image, "{filter:?}");
+            assert_eq!(framed.image.get_pixel(9, 5).0, [0.0; 3]);
+        }
+    }
+
+    #[test]
+    fn crops_outside_the_image_are_rejected() {
+        let mut world = spheres();
+        let cam = Camera {
+            image_width: 48,
+            ..Camera::default()
+        };
+        for crop in [
+            Crop::new(48, 0, 8, 8),
+            Crop::new(0, 27, 8, 8),
+            Crop::new(4, 4, 0, 8),
+        ] {
+            let result = cam
+                .renderer(1, 1)
+                .with_progress(|_: &ProgressEvent| {})
+                .with_crop(crop)
+                .render_frame(&mut world);
+            assert!(matches!(result, Err(RenderError::EmptyCrop)), "{crop:?}");
+        }
+    }
+
+    #[test]
+    fn samples_on_pixel_corners_land_in_their_own_pixel() {
+        // Nothing in view but a white sky, so every pixel that gets a sample is white.
+        let mut world = Scene::new();
+        world.add(Sphere::new(
+            Point3::new(0.0, 0.0, 5.0),
+            1.0,
+            Lambertian::new(Colour::WHITE),
+        ));
+        let cam = Camera {
+            image_width: 8,
+            aspect_ratio: 1.0,
+            background: SolidColour::new(Colour::WHITE),
+            ..Camera::default()
+        };
+        let filters = [
+            ReconstructionFilter::default(),
+            ReconstructionFilter::Gaussian {
+                radius: 1.5,
+                sigma: 0.5,
+            },
+        ];
+        for filter in filters {
+            let frame = cam
+                .renderer(1, 1)
+                .with_progress(|_: &ProgressEvent| {})
+                .with_sampler(CornerSampler)
+                .with_reconstruction_filter(filter)
+                .render_frame(&mut world)
+                .unwrap();
+            for (x, y, pixel) in frame.image.enumerate_pixels() {
+                assert!(
+                    pixel.0.iter().all(|&c| (c - 1.0).abs() < 1e-6),
+                    "{filter:?} at ({x}, {y}): {pixel:?}"
+                );
+            }
         }
     }
 }
@@ -0,0 +1,457 @@
+use std::fmt::Debug;
+use std::sync::OnceLock;
+
+// Samplers are pure functions of their arguments, so a render does not depend on the order in
+// which threads pick up pixels.
+pub trait Sampler: Debug + Send + Sync {
+    fn sample_1d(&self, seed: u64, pixel: (usize, usize), index: usize, dimension: usize) -> f64;
+
+    fn sample_2d(
+        &self,
+        seed: u64,
+        pixel: (usize, usize),
+        index: usize,
+        dimension: usize,
+    ) -> (f64, f64) {
+        (
+            self.sample_1d(seed, pixel, index, dimension),
+            self.sample_1d(seed, pixel, index, dimension + 1),
+        )
+    }
+}
+
+// The dimensions consumed by one sample of one pixel, handed down the path in order.
+pub struct SampleStream<'a> {
+    sampler: &'a dyn Sampler,
+    seed: u64,
+    pixel: (usize, usize),
+    index: usize,
+    dimension: usize,
+}
+
+impl<'a> SampleStream<'a> {
+    pub fn new(sampler: &'a dyn Sampler, seed: u64, x: usize, y: usize, index: usize) -> Self {
+        Self {
+            sampler,
+            seed,
+            pixel: (x, y),
+            index,
+            dimension: 0,
+        }
+    }
+
+    pub fn next_1d(&mut self) -> f64 {
+        let u = self
+            .sampler
+            .sample_1d(self.seed, self.pixel, self.index, self.dimension);
+        self.dimension += 1;
+        u
+    }
+
+    pub fn next_2d(&mut self) -> (f64, f64) {
+        let u = self
+            .sampler
+            .sample_2d(self.seed, self.pixel, self.index, self.dimension);
+        self.dimension += 2;
+        u
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct IndependentSampler;
+
+impl Sampler for IndependentSampler {
+    fn sample_1d(&self, seed: u64, pixel: (usize, usize), index: usize, dimension: usize) -> f64 {
+        unit(hash(&[
+            seed,
+            pixel.0 as u64,
+            pixel.1 as u64,
+            index as u64,
+            dimension as u64,
+        ]))
+    }
+}
+
+// Jittered strata for the pixel position, independent samples for everything else.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct StratifiedSampler {
+    pub strata: usize,
+}
+
+impl StratifiedSampler {
+    pub fn new(samples_per_pixel: usize) -> Self {
+        Self {
+            strata: ((samples_per_pixel as f64).sqrt() as usize).max(1),
+        }
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn sample_1d(&self, seed: u64, pixel: (usize, usize), index: usize, dimension: usize) -> f64 {
+        let jitter = IndependentSampler.sample_1d(seed, pixel, index, dimension);
+        let stratum = index % (self.strata * self.strata);
+        match dimension {
+            0 => ((stratum % self.strata) as f64 + jitter) / self.strata as f64,
+            1 => ((stratum / self.strata) as f64 + jitter) / self.strata as f64,
+            _ => jitter,
+        }
+    }
+}
+
+// Radical inverses in successive prime bases, with digits scrambled per pixel so neighbouring
+// pixels and the higher bases do not correlate.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct HaltonSampler;
+
+impl HaltonSampler {
+    const PRIMES: [u64; 64] = [
+        2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89,
+        97, 101, 103, 107, 109, 113, 127, 131, 137, 139, 149, 151, 157, 163, 167, 173, 179, 181,
+        191, 193, 197, 199, 211, 223, 227, 229, 233, 239, 241, 251, 257, 263, 269, 271, 277, 281,
+        283, 293, 307, 311,
+    ];
+}
+
+impl Sampler for HaltonSampler {
+    fn sample_1d(&self, seed: u64, pixel: (usize, usize), index: usize, dimension: usize) -> f64 {
+        match Self::PRIMES.get(dimension) {
+            Some(&base) => {
+                let seed = hash(&[seed, pixel.0 as u64, pixel.1 as u64, dimension as u64]);
+                scrambled_radical_inverse(base, index as u64, seed)
+            }
+            // Beyond the table the sequence degrades anyway, so fall back to independent samples.
+            None => IndependentSampler.sample_1d(seed, pixel, index, dimension),
+        }
+    }
+}
+
+// Pairs of dimensions from the two-dimensional Sobol sequence, Owen-scrambled and shuffled per
+// pair so any number of dimensions can be padded together without correlation.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SobolSampler;
+
+impl Sampler for SobolSampler {
+    fn sample_1d(&self, seed: u64, pixel: (usize, usize), index: usize, dimension: usize) -> f64 {
+        self.sample_2d(seed, pixel, index, dimension).0
+    }
+
+    fn sample_2d(
+        &self,
+        seed: u64,
+        pixel: (usize, usize),
+        index: usize,
+        dimension: usize,
+    ) -> (f64, f64) {
+        let seed = hash(&[seed, pixel.0 as u64, pixel.1 as u64, dimension as u64]);
+        scrambled_sobol_2d(index as u32, seed)
+    }
+}
+
+// One scrambled Sobol point set shared by every pixel, rotated by a blue-noise tile so the
+// error left at low sample counts is spread as high-frequency noise.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BlueNoiseSampler;
+
+impl Sampler for BlueNoiseSampler {
+    fn sample_1d(&self, seed: u64, pixel: (usize, usize), index: usize, dimension: usize) -> f64 {
+        self.sample_2d(seed, pixel, index, dimension).0
+    }
+
+    fn sample_2d(
+        &self,
+        seed: u64,
+        pixel: (usize, usize),
+        index: usize,
+        dimension: usize,
+    ) -> (f64, f64) {
+        let seed = hash(&[seed, dimension as u64]);
+        let (u, v) = scrambled_sobol_2d(index as u32, seed);
+        let tile = blue_noise_tile();
+        let lookup = |offset: u64| {
+            let x = (pixel.0 as u64 + offset) as usize % TILE_SIZE;
+            let y = (pixel.1 as u64 + (offset >> 32)) as usize % TILE_SIZE;
+            tile[y * TILE_SIZE + x]
+        };
+        (
+            (u + lookup(hash(&[seed, 0]))).fract(),
+            (v + lookup(hash(&[seed, 1]))).fract(),
+        )
+    }
+}
+
+// Owen scrambling: each digit is permuted by a permutation hashed from the digits before it.
+fn scrambled_radical_inverse(base: u64, mut index: u64, seed: u64) -> f64 {
+    let recip = 1.0 / base as f64;
+    let (mut value, mut scale, mut prefix) = (0.0, recip, 0u64);
+    while 1.0 - scale < 1.0 {
+        let digit = permutation_element(index % base, base, hash(&[seed, prefix]) as u32);
+        value += digit as f64 * scale;
+        prefix = prefix.wrapping_mul(base).wrapping_add(digit + 1);
+        index /= base;
+        scale *= recip;
+    }
+    value.min(1.0 - f64::EPSILON)
+}
+
+// Kensler, "Correlated Multi-Jittered Sampling", 2013: element i of a hashed permutation of n.
+fn permutation_element(i: u64, n: u64, seed: u32) -> u64 {
+    let (n, p) = (n as u32, seed);
+    let mut w = n - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    let mut i = i as u32;
+    loop {
+        i ^= p;
+        i = i.wrapping_mul(0xe170893d);
+        i ^= p >> 16;
+        i ^= (i & w) >> 4;
+        i ^= p >> 8;
+        i = i.wrapping_mul(0x0929eb3f);
+        i ^= p >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | p >> 27);
+        i = i.wrapping_mul(0x6935fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dcb303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e501cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < n {
+            break;
+        }
+    }
+    (i.wrapping_add(p) % n) as u64
+}
+
+fn sobol(index: u32, dimension: usize) -> u32 {
+    let mut result = 0;
+    let mut direction = 1u32 << 31;
+    for bit in 0..32 {
+        if index & (1 << bit) != 0 {
+            result ^= direction;
+        }
+        direction = match dimension {
+            0 => direction >> 1,
+            _ => direction ^ (direction >> 1),
+        };
+    }
+    result
+}
+
+// Burley, "Practical Hash-based Owen Scrambling", 2020.
+fn laine_karras_permutation(mut x: u32, seed: u32) -> u32 {
+    x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50b47c);
+    x ^= x.wrapping_mul(0xb82f1e52);
+    x ^= x.wrapping_mul(0xc7afe638);
+    x ^= x.wrapping_mul(0x8d22f6e6);
+    x
+}
+
+fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+    laine_karras_permutation(x.reverse_bits(), seed).reverse_bits()
+}
+
+fn scrambled_sobol_2d(index: u32, seed: u64) -> (f64, f64) {
+    let index = nested_uniform_scramble(index, seed as u32);
+    let x = nested_uniform_scramble(sobol(index, 0), hash(&[seed, 0]) as u32);
+    let y = nested_uniform_scramble(sobol(index, 1), hash(&[seed, 1]) as u32);
+    (x as f64 / 4294967296.0, y as f64 / 4294967296.0)
+}
+
+fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
+
+pub(crate) fn hash(values: &[u64]) -> u64 {
+    values.iter().fold(0x9e3779b97f4a7c15, |h, &v| {
+        mix(h ^ v.wrapping_add(0x9e3779b97f4a7c15))
+    })
+}
+
+pub(crate) fn unit(bits: u64) -> f64 {
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+const TILE_SIZE: usize = 64;
+
+fn blue_noise_tile() -> &'static [f64] {
+    static TILE: OnceLock<Vec<f64>> = OnceLock::new();
+    TILE.get_or_init(void_and_cluster)
+}
+
+// Ulichney's void-and-cluster method on a torus, ranking every texel of the tile.
+fn void_and_cluster() -> Vec<f64> {
+    const N: usize = TILE_SIZE * TILE_SIZE;
+    const RADIUS: isize = 6;
+    const SIGMA: f64 = 1.5;
+    let kernel: Vec<(isize, isize, f64)> = (-RADIUS..=RADIUS)
+        .flat_map(|dy| (-RADIUS..=RADIUS).map(move |dx| (dx, dy)))
+        .map(|(dx, dy)| {
+            let r2 = (dx * dx + dy * dy) as f64;
+            (dx, dy, (-r2 / (2.0 * SIGMA * SIGMA)).exp())
+        })
+        .collect();
+    let splat = |energy: &mut [f64], i: usize, sign: f64| {
+        let (x, y) = ((i % TILE_SIZE) as isize, (i / TILE_SIZE) as isize);
+        for &(dx, dy, weight) in &kernel {
+            let nx = (x + dx).rem_euclid(TILE_SIZE as isize) as usize;
+            let ny = (y + dy).rem_euclid(TILE_SIZE as isize) as usize;
+            energy[ny * TILE_SIZE + nx] += sign * weight;
+        }
+    };
+    let extreme = |pattern: &[bool], energy: &[f64], set: bool, tightest: bool| {
+        (0..N)
+            .filter(|&i| pattern[i] == set)
+            .max_by(|&a, &b| {
+                let order = energy[a].total_cmp(&energy[b]);
+                if tightest { order } else { order.reverse() }
+            })
+            .unwrap()
+    };
+
+    let mut rng = fastrand::Rng::with_seed(0x5eed);
+    let mut pattern = vec![false; N];
+    let mut energy = vec![0.0; N];
+    let ones = N / 10;
+    while pattern.iter().filter(|&&p| p).count() < ones {
+        let i = rng.usize(0..N);
+        if !pattern[i] {
+            pattern[i] = true;
+            splat(&mut energy, i, 1.0);
+        }
+    }
+    // Relax the initial pattern by moving points from the tightest cluster into the largest void.
+    loop {
+        let cluster = extreme(&pattern, &energy, true, true);
+        pattern[cluster] = false;
+        splat(&mut energy, cluster, -1.0);
+        let void = extreme(&pattern, &energy, false, false);
+        pattern[void] = true;
+        splat(&mut energy, void, 1.0);
+        if void == cluster {
+            break;
+        }
+    }
+
+    let mut rank = vec![0; N];
+    let (mut phase_pattern, mut phase_energy) = (pattern.clone(), energy.clone());
+    for count in (0..ones).rev() {
+        let cluster = extreme(&phase_pattern, &phase_energy, true, true);
+        phase_pattern[cluster] = false;
+        splat(&mut phase_energy, cluster, -1.0);
+        rank[cluster] = count;
+    }
+    // Filling the largest void is the same as removing the tightest cluster of the minority
+    // zeros, so a single loop covers both remaining phases.
+    for count in ones..N {
+        let void = extreme(&pattern, &energy, false, false);
+        pattern[void] = true;
+        splat(&mut energy, void, 1.0);
+        rank[void] = count;
+    }
+    rank.into_iter()
+        .map(|r| (r as f64 + 0.5) / N as f64)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Which of n equal bins each value falls into, sorted.
+    fn bins(values: impl Iterator<Item = f64>, n: usize) -> Vec<usize> {
+        let mut bins: Vec<usize> = values.map(|v| (v * n as f64) as usize).collect();
+        bins.sort();
+        bins
+    }
+
+    #[test]
+    fn samplers_are_deterministic_and_in_range() {
+        let samplers: [&dyn Sampler; 5] = [
+            &IndependentSampler,
+            &StratifiedSampler::new(16),
+            &HaltonSampler,
+            &SobolSampler,
+            &BlueNoiseSampler,
+        ];
+        for sampler in samplers {
+            for index in 0..64 {
+                for dimension in 0..8 {
+                    let u = sampler.sample_1d(3, (5, 7), index, dimension);
+                    assert!((0.0..1.0).contains(&u), "{sampler:?} gave {u}");
+                    assert_eq!(u, sampler.sample_1d(3, (5, 7), index, dimension));
+                }
+            }
+            let a: Vec<f64> = (0..16)
+                .map(|i| sampler.sample_1d(1, (0, 0), i, 2))
+                .collect();
+            let b: Vec<f64> = (0..16)
+                .map(|i| sampler.sample_1d(2, (0, 0), i, 2))
+                .collect();
+            assert_ne!(a, b, "{sampler:?} ignores the seed");
+        }
+    }
+
+    #[test]
+    fn stratified_sampler_visits_every_stratum_once() {
+        let sampler = StratifiedSampler::new(16);
+        let mut cells: Vec<(usize, usize)> = (0..16)
+            .map(|index| {
+                let (u, v) = sampler.sample_2d(0, (1, 2), index, 0);
+                ((u * 4.0) as usize, (v * 4.0) as usize)
+            })
+            .collect();
+        cells.sort();
+        let expected: Vec<(usize, usize)> =
+            (0..4).flat_map(|x| (0..4).map(move |y| (x, y))).collect();
+        assert_eq!(cells, expected);
+    }
+
+    #[test]
+    fn halton_prefixes_are_stratified() {
+        let sampler = HaltonSampler;
+        let base2 = (0..16).map(|index| sampler.sample_1d(0, (3, 4), index, 0));
+        assert_eq!(bins(base2, 16), (0..16).collect::<Vec<_>>());
+        let base3 = (0..9).map(|index| sampler.sample_1d(0, (3, 4), index, 1));
+        assert_eq!(bins(base3, 9), (0..9).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn sobol_prefixes_are_nets() {
+        let sampler = SobolSampler;
+        for dimension in [0, 2, 5] {
+            let points: Vec<(f64, f64)> = (0..16)
+                .map(|index| sampler.sample_2d(9, (6, 1), index, dimension))
+                .collect();
+            // Every elementary interval of area 1/16 holds exactly one of the first 16 points.
+            for (nx, ny) in [(16, 1), (8, 2), (4, 4), (2, 8), (1, 16)] {
+                let mut cells: Vec<(usize, usize)> = points
+                    .iter()
+                    .map(|&(u, v)| ((u * nx as f64) as usize, (v * ny as f64) as usize))
+                    .collect();
+                cells.sort();
+                cells.dedup();
+                assert_eq!(cells.len(), 16, "{nx}x{ny} strata in dimension {dimension}");
+            }
+        }
+    }
+
+    #[test]
+    fn blue_noise_tile_ranks_every_texel_once() {
+        let tile = blue_noise_tile();
+        assert_eq!(tile.len(), TILE_SIZE * TILE_SIZE);
+        assert_eq!(
+            bins(tile.iter().copied(), tile.len()),
+            (0..tile.len()).collect::<Vec<_>>()
+        );
+    }
+}
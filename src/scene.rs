@@ -1,7 +1,9 @@
 use crate::bounding_box::AaBb;
 use crate::linalg::{Point3, Vec3};
+use crate::material::Material;
 use crate::objects::{Collection, HitRecord, Hittable, Interval, IntoPrimitives, Object};
 use crate::ray::Ray;
+use crate::sampler::SampleStream;
 
 #[derive(Debug)]
 pub struct Scene(Collection);
@@ -25,7 +27,7 @@ impl Scene {
 }
 
 impl Hittable for Scene {
-    fn hit(&self, ray: &Ray, range: Interval) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, range: Interval) -> Option<HitRecord<'_>> {
         let mut temp_rec: Option<HitRecord> = None;
         let mut closest_so_far = range.max;
 
@@ -41,21 +43,29 @@ impl Hittable for Scene {
     fn bbox(&self) -> AaBb {
         self.0.bbox
     }
-    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, time: f64) -> f64 {
         let weight = 1.0 / self.0.objects.len() as f64;
         self.0
             .objects
             .iter()
-            .map(|o| o.0.pdf_value(origin, direction) * weight)
+            .map(|o| o.0.pdf_value(origin, direction, time) * weight)
             .sum()
     }
-    fn random(&self, origin: &Point3) -> Vec3 {
-        self.0.objects[fastrand::usize(0..self.0.objects.len())]
+    fn random(&self, origin: &Point3, time: f64, samples: &mut SampleStream) -> Vec3 {
+        let n = self.0.objects.len();
+        self.0.objects[((samples.next_1d() * n as f64) as usize).min(n - 1)]
             .0
-            .random(origin)
+            .random(origin, time, samples)
     }
     fn lights(&self) -> Collection {
         let objects = self.0.objects.iter().flat_map(|o| o.0.lights()).collect();
         Collection::with_objects(objects)
     }
+    fn materials(&self) -> Vec<Material> {
+        self.0
+            .objects
+            .iter()
+            .flat_map(|o| o.0.materials())
+            .collect()
+    }
 }
@@ -0,0 +1,114 @@
+use std::cell::Cell;
+use std::ops::AddAssign;
+use std::time::Duration;
+
+use crate::progress::Phase;
+
+#[derive(Debug, Default, Clone)]
+pub struct RenderStatistics {
+    pub rays: u64,
+    pub shadow_rays: u64,
+    pub paths: u64,
+    pub max_depth_paths: u64,
+    pub roulette_paths: u64,
+    pub bvh_nodes_tested: u64,
+    pub primitives_tested: u64,
+    pub timings: Vec<(Phase, Duration)>,
+    pub total_time: Duration,
+}
+
+impl RenderStatistics {
+    pub fn average_path_length(&self) -> f64 {
+        self.rays as f64 / self.paths.max(1) as f64
+    }
+
+    pub fn phase_time(&self, phase: Phase) -> Duration {
+        self.timings
+            .iter()
+            .filter(|(p, _)| *p == phase)
+            .map(|(_, t)| *t)
+            .sum()
+    }
+
+    pub fn tracing_time(&self) -> Duration {
+        self.timings
+            .iter()
+            .filter(|(p, _)| matches!(p, Phase::Tracing { .. }))
+            .map(|(_, t)| *t)
+            .sum()
+    }
+}
+
+impl AddAssign<Counters> for RenderStatistics {
+    fn add_assign(&mut self, counters: Counters) {
+        self.rays += counters.rays;
+        self.shadow_rays += counters.shadow_rays;
+        self.paths += counters.paths;
+        self.max_depth_paths += counters.max_depth_paths;
+        self.roulette_paths += counters.roulette_paths;
+        self.bvh_nodes_tested += counters.bvh_nodes_tested;
+        self.primitives_tested += counters.primitives_tested;
+    }
+}
+
+// Counters live in a thread local so the hot loops never contend on shared state.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct Counters {
+    pub rays: u64,
+    pub shadow_rays: u64,
+    pub paths: u64,
+    pub max_depth_paths: u64,
+    pub roulette_paths: u64,
+    pub bvh_nodes_tested: u64,
+    pub primitives_tested: u64,
+}
+
+impl AddAssign for Counters {
+    fn add_assign(&mut self, other: Self) {
+        self.rays += other.rays;
+        self.shadow_rays += other.shadow_rays;
+        self.paths += other.paths;
+        self.max_depth_paths += other.max_depth_paths;
+        self.roulette_paths += other.roulette_paths;
+        self.bvh_nodes_tested += other.bvh_nodes_tested;
+        self.primitives_tested += other.primitives_tested;
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Counter {
+    Rays,
+    ShadowRays,
+    Paths,
+    MaxDepthPaths,
+    RoulettePaths,
+    BvhNodesTested,
+    PrimitivesTested,
+}
+
+// One cell per counter, so bumping one in the traversal loop touches a single word.
+thread_local! {
+    static COUNTERS: [Cell<u64>; 7] = const { [const { Cell::new(0) }; 7] };
+}
+
+pub(crate) fn add(counter: Counter, n: u64) {
+    COUNTERS.with(|counters| {
+        let cell = &counters[counter as usize];
+        cell.set(cell.get() + n);
+    });
+}
+
+pub(crate) fn take() -> Counters {
+    COUNTERS.with(|counters| {
+        let take = |counter: Counter| counters[counter as usize].take();
+        Counters {
+            rays: take(Counter::Rays),
+            shadow_rays: take(Counter::ShadowRays),
+            paths: take(Counter::Paths),
+            max_depth_paths: take(Counter::MaxDepthPaths),
+            roulette_paths: take(Counter::RoulettePaths),
+            bvh_nodes_tested: take(Counter::BvhNodesTested),
+            primitives_tested: take(Counter::PrimitivesTested),
+        }
+    })
+}
@@ -0,0 +1,154 @@
+use std::path::Path;
+
+use image::{GenericImage, Rgb32FImage};
+
+use crate::effects::{RenderFilter, TrivialFilter};
+use crate::error::RenderError;
+use crate::render::{Camera, Frame, Projection, Renderer};
+use crate::scene::Scene;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Convergence {
+    Parallel,
+    ToeIn { distance: f64 },
+    OffAxis { distance: f64 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StereoLayout {
+    Separate,
+    SideBySide,
+    TopBottom,
+}
+
+#[derive(Debug, Clone)]
+pub struct StereoRig {
+    pub camera: Camera,
+    pub interocular: f64,
+    pub convergence: Convergence,
+    pub layout: StereoLayout,
+}
+
+impl StereoRig {
+    pub fn new(camera: Camera) -> Self {
+        Self {
+            convergence: Convergence::OffAxis {
+                distance: camera.focus_dist,
+            },
+            camera,
+            interocular: 0.064,
+            layout: StereoLayout::SideBySide,
+        }
+    }
+
+    pub fn renderer(&self, samples_per_pixel: usize, max_depth: usize) -> StereoRenderer {
+        StereoRenderer {
+            left: self.eye(-0.5 * self.interocular, samples_per_pixel, max_depth),
+            right: self.eye(0.5 * self.interocular, samples_per_pixel, max_depth),
+            layout: self.layout,
+        }
+    }
+
+    fn eye(&self, offset: f64, samples_per_pixel: usize, max_depth: usize) -> Renderer {
+        if self.camera.projection == Projection::Equirectangular {
+            return self
+                .camera
+                .renderer(samples_per_pixel, max_depth)
+                .with_ods_offset(offset);
+        }
+        let w = (self.camera.lookfrom - self.camera.lookat).normalize();
+        let u = self.camera.up.cross(&w).normalize();
+        let shift = offset * u;
+        let mut camera = self.camera.clone();
+        camera.lookfrom += shift;
+        match self.convergence {
+            Convergence::Parallel | Convergence::OffAxis { .. } => camera.lookat += shift,
+            Convergence::ToeIn { distance } => camera.lookat = self.camera.lookfrom - distance * w,
+        }
+        let renderer = camera.renderer(samples_per_pixel, max_depth);
+        match self.convergence {
+            // Slide the viewport back so both frusta meet at the convergence distance.
+            Convergence::OffAxis { distance } => {
+                renderer.shift_viewport(-shift * (camera.focus_dist / distance))
+            }
+            _ => renderer,
+        }
+    }
+}
+
+pub struct StereoRenderer {
+    pub left: Renderer,
+    pub right: Renderer,
+    pub layout: StereoLayout,
+}
+
+pub struct StereoFrame {
+    pub left: Frame,
+    pub right: Frame,
+}
+
+impl StereoFrame {
+    pub fn images(&self, layout: StereoLayout) -> Vec<Rgb32FImage> {
+        let (left, right) = (&self.left.image, &self.right.image);
+        let (width, height) = left.dimensions();
+        let mut combined = match layout {
+            StereoLayout::Separate => return vec![left.clone(), right.clone()],
+            StereoLayout::SideBySide => Rgb32FImage::new(2 * width, height),
+            StereoLayout::TopBottom => Rgb32FImage::new(width, 2 * height),
+        };
+        let (x, y) = match layout {
+            StereoLayout::SideBySide => (width, 0),
+            _ => (0, height),
+        };
+        combined.copy_from(left, 0, 0).unwrap();
+        combined.copy_from(right, x, y).unwrap();
+        vec![combined]
+    }
+}
+
+impl StereoRenderer {
+    pub fn configure(self, configure: impl Fn(Renderer) -> Renderer) -> Self {
+        Self {
+            left: configure(self.left),
+            right: configure(self.right),
+            layout: self.layout,
+        }
+    }
+
+    pub fn render_frames_with_filter<F>(
+        &self,
+        world: &mut Scene,
+        filter: F,
+    ) -> Result<StereoFrame, RenderError>
+    where
+        F: RenderFilter,
+    {
+        Ok(StereoFrame {
+            left: self.left.render_frame_with_filter(world, &filter)?,
+            right: self.right.render_frame_with_filter(world, &filter)?,
+        })
+    }
+
+    pub fn render_frames(&self, world: &mut Scene) -> Result<StereoFrame, RenderError> {
+        self.render_frames_with_filter(world, TrivialFilter)
+    }
+
+    pub fn render_to_file<F>(&self, world: &mut Scene, path: F) -> Result<(), RenderError>
+    where
+        F: AsRef<Path>,
+    {
+        let frames = self.render_frames(world)?;
+        let images = frames.images(self.layout);
+        if let [image] = images.as_slice() {
+            return self.left.save(image, path);
+        }
+        let path = path.as_ref();
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let extension = path.extension().unwrap_or_default().to_string_lossy();
+        for (image, eye) in images.iter().zip(["left", "right"]) {
+            let eye_path = path.with_file_name(format!("{stem}_{eye}.{extension}"));
+            self.left.save(image, eye_path)?;
+        }
+        Ok(())
+    }
+}
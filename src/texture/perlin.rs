@@ -14,7 +14,8 @@ pub(crate) struct Perlin {
 }
 
 impl Perlin {
-    fn new() -> Self {
+    pub(crate) fn new(seed: u64) -> Self {
+        let mut rng = fastrand::Rng::with_seed(seed);
         let mut res = Self {
             rand: [Vec3::ZERO; POINT_COUNT],
             perm_x: [0; POINT_COUNT],
@@ -22,11 +23,11 @@ impl Perlin {
             perm_z: [0; POINT_COUNT],
         };
         for v in res.rand.iter_mut() {
-            *v = random_unit_vector();
+            *v = random_unit_vector(&mut rng);
         }
-        Self::generate(&mut res.perm_x);
-        Self::generate(&mut res.perm_y);
-        Self::generate(&mut res.perm_z);
+        Self::generate(&mut res.perm_x, &mut rng);
+        Self::generate(&mut res.perm_y, &mut rng);
+        Self::generate(&mut res.perm_z, &mut rng);
         res
     }
 
@@ -89,23 +90,17 @@ impl Perlin {
         acc
     }
 
-    fn generate(p: &mut [u8]) {
+    fn generate(p: &mut [u8], rng: &mut fastrand::Rng) {
         for (i, v) in p.iter_mut().enumerate() {
             *v = i as u8;
         }
-        Self::permute(p);
+        Self::permute(p, rng);
     }
 
-    fn permute(p: &mut [u8]) {
+    fn permute(p: &mut [u8], rng: &mut fastrand::Rng) {
         for i in (1..POINT_COUNT).rev() {
-            let tgt = fastrand::u8(0..i as u8);
+            let tgt = rng.u8(0..i as u8);
             p.swap(i, tgt as usize);
         }
     }
 }
-
-impl Default for Perlin {
-    fn default() -> Self {
-        Self::new()
-    }
-}
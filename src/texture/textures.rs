@@ -119,9 +119,9 @@ pub struct NoiseTexture<T> {
 }
 
 impl NoiseTexture<Plain> {
-    pub fn plain(scale: f64) -> Texture {
+    pub fn plain(scale: f64, seed: u64) -> Texture {
         Texture::new(Arc::new(Self {
-            perlin: Perlin::default(),
+            perlin: Perlin::new(seed),
             scale,
             kind: Plain,
         }))
@@ -129,9 +129,9 @@ impl NoiseTexture<Plain> {
 }
 
 impl NoiseTexture<Turbulence> {
-    pub fn turbulence(scale: f64, depth: usize) -> Texture {
+    pub fn turbulence(scale: f64, depth: usize, seed: u64) -> Texture {
         Texture::new(Arc::new(Self {
-            perlin: Perlin::default(),
+            perlin: Perlin::new(seed),
             scale,
             kind: Turbulence(depth),
         }))
@@ -139,9 +139,9 @@ impl NoiseTexture<Turbulence> {
 }
 
 impl NoiseTexture<Marble> {
-    pub fn marble(scale: f64) -> Texture {
+    pub fn marble(scale: f64, seed: u64) -> Texture {
         Texture::new(Arc::new(Self {
-            perlin: Perlin::default(),
+            perlin: Perlin::new(seed),
             scale,
             kind: Marble,
         }))
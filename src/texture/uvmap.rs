@@ -1,13 +1,15 @@
 use crate::{
     bounding_box::AaBb,
     linalg::{Point3, Vec3},
+    material::Material,
     objects::{Collection, HitRecord, Hittable, Interval, Object},
     ray::Ray,
+    sampler::SampleStream,
 };
 use std::sync::Arc;
 
 impl Hittable for UVTriangle {
-    fn hit(&self, ray: &Ray, range: Interval) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, range: Interval) -> Option<HitRecord<'_>> {
         self.obj.hit(ray, range).map(|rec| {
             let p = rec.u * self.u + rec.v * self.v + self.q;
             HitRecord {
@@ -23,11 +25,14 @@ impl Hittable for UVTriangle {
     fn lights(&self) -> Collection {
         self.obj.lights()
     }
-    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
-        self.obj.pdf_value(origin, direction)
+    fn materials(&self) -> Vec<Material> {
+        self.obj.materials()
     }
-    fn random(&self, origin: &Point3) -> Vec3 {
-        self.obj.random(origin)
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, time: f64) -> f64 {
+        self.obj.pdf_value(origin, direction, time)
+    }
+    fn random(&self, origin: &Point3, time: f64, samples: &mut SampleStream) -> Vec3 {
+        self.obj.random(origin, time, samples)
     }
 }
 
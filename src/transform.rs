@@ -1,10 +1,13 @@
 use std::sync::Arc;
 
 use crate::{
+    animation::Keyframes,
     bounding_box::AaBb,
     linalg::{Mat3, Point3, Vec3},
+    material::Material,
     objects::{Collection, HitRecord, Hittable, Interval, IntoPrimitives, Object},
     ray::Ray,
+    sampler::SampleStream,
 };
 
 #[derive(Debug, Clone)]
@@ -34,8 +37,8 @@ impl Hittable for Translate {
         self.bbox
     }
 
-    fn hit(&self, ray: &Ray, range: Interval) -> Option<HitRecord> {
-        let offset_ray = Ray::time_dependent(ray.origin - self.offset, ray.direction, ray.time);
+    fn hit(&self, ray: &Ray, range: Interval) -> Option<HitRecord<'_>> {
+        let offset_ray = ray.transformed(ray.origin - self.offset, ray.direction);
         let mut hit = self.object.hit(&offset_ray, range);
         if let Some(ref mut rec) = hit {
             rec.p += self.offset;
@@ -43,16 +46,20 @@ impl Hittable for Translate {
         hit
     }
 
-    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
-        self.object.pdf_value(&(*origin - self.offset), direction)
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, time: f64) -> f64 {
+        self.object
+            .pdf_value(&(*origin - self.offset), direction, time)
     }
-    fn random(&self, origin: &Point3) -> Vec3 {
-        self.object.random(&(*origin - self.offset))
+    fn random(&self, origin: &Point3, time: f64, samples: &mut SampleStream) -> Vec3 {
+        self.object.random(&(*origin - self.offset), time, samples)
     }
 
     fn lights(&self) -> Collection {
         Translate::new(self.object.lights(), self.offset)
     }
+    fn materials(&self) -> Vec<Material> {
+        self.object.materials()
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -116,12 +123,8 @@ impl Hittable for Rotate {
         self.bbox
     }
 
-    fn hit(&self, ray: &Ray, range: Interval) -> Option<HitRecord> {
-        let rotated_ray = Ray::time_dependent(
-            self.mat_t * ray.origin,
-            self.mat_t * ray.direction,
-            ray.time,
-        );
+    fn hit(&self, ray: &Ray, range: Interval) -> Option<HitRecord<'_>> {
+        let rotated_ray = ray.transformed(self.mat_t * ray.origin, self.mat_t * ray.direction);
         let mut hit = self.object.hit(&rotated_ray, range);
         if let Some(ref mut rec) = hit {
             rec.p = self.mat * rec.p;
@@ -130,14 +133,229 @@ impl Hittable for Rotate {
         hit
     }
 
-    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
-        self.object
-            .pdf_value(&(self.mat_t * (*origin)), &(self.mat_t * (*direction)))
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, time: f64) -> f64 {
+        self.object.pdf_value(
+            &(self.mat_t * (*origin)),
+            &(self.mat_t * (*direction)),
+            time,
+        )
     }
-    fn random(&self, origin: &Point3) -> Vec3 {
-        self.mat * self.object.random(&(self.mat_t * (*origin)))
+    fn random(&self, origin: &Point3, time: f64, samples: &mut SampleStream) -> Vec3 {
+        self.mat * self.object.random(&(self.mat_t * (*origin)), time, samples)
     }
     fn lights(&self) -> Collection {
         Rotate::from_matrix(self.object.lights(), self.mat)
     }
+    fn materials(&self) -> Vec<Material> {
+        self.object.materials()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AnimatedTranslate {
+    object: Object,
+    offset: Keyframes<Vec3>,
+    bbox: AaBb,
+}
+
+impl Translate {
+    pub fn animated(object: impl IntoPrimitives, offset: Keyframes<Vec3>) -> Collection {
+        let mut result = Collection::new();
+        for obj in object.primitives() {
+            let bbox = offset
+                .hulls(1)
+                .into_iter()
+                .flatten()
+                .fold(AaBb::default(), |bbox, p| {
+                    AaBb::enclosing(&bbox, &(obj.bbox() + p))
+                });
+            result.add(Object(Arc::new(AnimatedTranslate {
+                object: obj,
+                offset: offset.clone(),
+                bbox,
+            })));
+        }
+        result
+    }
+}
+
+impl Hittable for AnimatedTranslate {
+    fn bbox(&self) -> AaBb {
+        self.bbox
+    }
+
+    fn hit(&self, ray: &Ray, range: Interval) -> Option<HitRecord<'_>> {
+        let offset = self.offset.at(ray.time);
+        let offset_ray = ray.transformed(ray.origin - offset, ray.direction);
+        let mut hit = self.object.hit(&offset_ray, range);
+        if let Some(ref mut rec) = hit {
+            rec.p += offset;
+        }
+        hit
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, time: f64) -> f64 {
+        let offset = self.offset.at(time);
+        self.object.pdf_value(&(*origin - offset), direction, time)
+    }
+    fn random(&self, origin: &Point3, time: f64, samples: &mut SampleStream) -> Vec3 {
+        let offset = self.offset.at(time);
+        self.object.random(&(*origin - offset), time, samples)
+    }
+
+    fn lights(&self) -> Collection {
+        Translate::animated(self.object.lights(), self.offset.clone())
+    }
+    fn materials(&self) -> Vec<Material> {
+        self.object.materials()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AnimatedRotate {
+    object: Object,
+    angles: Keyframes<Vec3>,
+    bbox: AaBb,
+}
+
+impl Rotate {
+    pub fn animated(object: impl IntoPrimitives, angles: Keyframes<Vec3>) -> Collection {
+        let mut result = Collection::new();
+        for obj in object.primitives() {
+            // Points of the object are at most this far from the centre of rotation.
+            let radius = obj
+                .bbox()
+                .corners()
+                .iter()
+                .map(|c| c.length())
+                .fold(0.0, f64::max);
+            let bbox = angles
+                .hulls(32)
+                .into_iter()
+                .fold(AaBb::default(), |bbox, hull| {
+                    let [lo, hi] = [f64::min, f64::max].map(|f| {
+                        hull.iter().fold(hull[0], |a, b| {
+                            Vec3::new(f(a.x, b.x), f(a.y, b.y), f(a.z, b.z))
+                        })
+                    });
+                    let a = 0.5 * (lo + hi);
+                    let mut rotated = Self::bbox_rotate(obj.clone(), Mat3::rotation(a.x, a.y, a.z));
+                    // Turning by up to half the range about each axis moves a point by at most
+                    // the arc it sweeps.
+                    let spread = hi - lo;
+                    let pad = radius * 0.5 * (spread.x + spread.y + spread.z).to_radians();
+                    for axis in [&mut rotated.x, &mut rotated.y, &mut rotated.z] {
+                        axis.extend(pad);
+                    }
+                    AaBb::enclosing(&bbox, &rotated)
+                });
+            result.add(Object(Arc::new(AnimatedRotate {
+                object: obj,
+                angles: angles.clone(),
+                bbox,
+            })));
+        }
+        result
+    }
+}
+
+impl AnimatedRotate {
+    fn matrix(&self, time: f64) -> Mat3 {
+        let a = self.angles.at(time);
+        Mat3::rotation(a.x, a.y, a.z)
+    }
+}
+
+impl Hittable for AnimatedRotate {
+    fn bbox(&self) -> AaBb {
+        self.bbox
+    }
+
+    fn hit(&self, ray: &Ray, range: Interval) -> Option<HitRecord<'_>> {
+        let mat = self.matrix(ray.time);
+        let mat_t = mat.transpose();
+        let rotated_ray = ray.transformed(mat_t * ray.origin, mat_t * ray.direction);
+        let mut hit = self.object.hit(&rotated_ray, range);
+        if let Some(ref mut rec) = hit {
+            rec.p = mat * rec.p;
+            rec.normal = mat * rec.normal;
+        }
+        hit
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3, time: f64) -> f64 {
+        let mat_t = self.matrix(time).transpose();
+        self.object
+            .pdf_value(&(mat_t * (*origin)), &(mat_t * (*direction)), time)
+    }
+    fn random(&self, origin: &Point3, time: f64, samples: &mut SampleStream) -> Vec3 {
+        let mat = self.matrix(time);
+        mat * self
+            .object
+            .random(&(mat.transpose() * (*origin)), time, samples)
+    }
+    fn lights(&self) -> Collection {
+        Rotate::animated(self.object.lights(), self.angles.clone())
+    }
+    fn materials(&self) -> Vec<Material> {
+        self.object.materials()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::Interpolation;
+    use crate::colour::Colour;
+    use crate::material::{DiffuseLight, Lambertian};
+    use crate::objects::Quad;
+    use crate::sampler::IndependentSampler;
+
+    #[test]
+    fn animated_rotation_stays_inside_its_bounds() {
+        let quad = Quad::new(
+            Point3::new(2.0, 0.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Lambertian::new(Colour::WHITE),
+        );
+        let corners = quad.bbox().corners();
+        let angles = Keyframes::new(Interpolation::CatmullRom, 0.0, Vec3::ZERO)
+            .key(1.0, Vec3::new(0.0, 170.0, 0.0))
+            .key(2.0, Vec3::new(30.0, 10.0, 0.0));
+        let animated = Rotate::animated(quad, angles.clone());
+        let bbox = animated.objects[0].bbox();
+        for k in 0..=2000 {
+            let a = angles.at(k as f64 / 1000.0);
+            for corner in &corners {
+                let p = Mat3::rotation(a.x, a.y, a.z) * *corner;
+                for axis in 0..3 {
+                    assert!(bbox[axis].min <= p[axis] && p[axis] <= bbox[axis].max);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn animated_lights_are_sampled_at_the_ray_time() {
+        let light = Quad::new(
+            Point3::new(-0.5, 5.0, -0.5),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 1.0),
+            DiffuseLight::from_colour(Colour::WHITE),
+        );
+        let offset = Keyframes::new(Interpolation::Linear, 0.0, Vec3::ZERO)
+            .key(1.0, Vec3::new(10.0, 0.0, 0.0));
+        let animated = Translate::animated(light, offset);
+        let light = &animated.objects[0];
+        let origin = Point3::ZERO;
+        let up = Vec3::new(0.0, 1.0, 0.0);
+        let moved = Vec3::new(10.0, 5.0, 0.0);
+        assert!(light.pdf_value(&origin, &up, 0.0) > 0.0);
+        assert_eq!(light.pdf_value(&origin, &up, 1.0), 0.0);
+        assert!(light.pdf_value(&origin, &moved, 1.0) > 0.0);
+        let mut samples = SampleStream::new(&IndependentSampler, 0, 0, 0, 0);
+        let direction = light.random(&origin, 1.0, &mut samples);
+        assert!(light.pdf_value(&origin, &direction, 1.0) > 0.0);
+    }
 }
@@ -6,6 +6,7 @@ use crate::{
     material::{Isotropic, Material},
     objects::{Collection, HitRecord, Hittable, Interval, IntoPrimitives, Object},
     ray::Ray,
+    sampler::{SampleStream, hash, unit},
     texture::SolidColour,
 };
 
@@ -43,12 +44,13 @@ impl Hittable for ConstantMedium {
         self.boundary.bbox()
     }
 
-    fn hit(&self, ray: &Ray, range: Interval) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, range: Interval) -> Option<HitRecord<'_>> {
         if let Some(ref mut rec1) = self.boundary.hit(ray, Interval::universe()) {
             if let Some(ref mut rec2) = self
                 .boundary
                 .hit(ray, Interval::new(rec1.t + 0.0001, f64::INFINITY))
             {
+                let entry = rec1.t;
                 if rec1.t < range.min {
                     rec1.t = range.min;
                 }
@@ -63,7 +65,22 @@ impl Hittable for ConstantMedium {
                 }
                 let ray_length = ray.direction.length();
                 let distance_inside_boundary = (rec2.t - rec1.t) * ray_length;
-                let hit_distance = self.neg_inv_density * fastrand::f64().ln();
+                // The free path comes from the sample the integrator drew for this ray, scrambled by
+                // where the ray enters so that media one behind the other scatter independently.
+                // Rays traced outside a sample stream fall back to a hash of the ray itself.
+                let u = match ray.sample() {
+                    Some(u) => unit(hash(&[u.to_bits(), entry.to_bits()])),
+                    None => unit(hash(&[
+                        ray.origin.x.to_bits(),
+                        ray.origin.y.to_bits(),
+                        ray.origin.z.to_bits(),
+                        ray.direction.x.to_bits(),
+                        ray.direction.y.to_bits(),
+                        ray.direction.z.to_bits(),
+                        ray.time.to_bits(),
+                    ])),
+                };
+                let hit_distance = self.neg_inv_density * (1.0 - u).ln();
                 if hit_distance > distance_inside_boundary {
                     return None;
                 }
@@ -76,6 +93,8 @@ impl Hittable for ConstantMedium {
                     material: self.phase_function.as_ref(),
                     u: 0.0,
                     v: 0.0,
+                    object_id: 0,
+                    material_id: 0,
                 })
             } else {
                 None
@@ -85,10 +104,10 @@ impl Hittable for ConstantMedium {
         }
     }
 
-    fn pdf_value(&self, _origin: &Point3, _direction: &Vec3) -> f64 {
+    fn pdf_value(&self, _origin: &Point3, _direction: &Vec3, _time: f64) -> f64 {
         0.0
     }
-    fn random(&self, _origin: &Point3) -> Vec3 {
+    fn random(&self, _origin: &Point3, _time: f64, _samples: &mut SampleStream) -> Vec3 {
         Vec3::EX
     }
     fn lights(&self) -> Collection {
@@ -98,4 +117,53 @@ impl Hittable for ConstantMedium {
         }
         res
     }
+    fn materials(&self) -> Vec<Material> {
+        vec![self.phase_function.clone()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::Sphere;
+    use crate::progress::ProgressEvent;
+    use crate::render::Camera;
+    use crate::scene::Scene;
+    use crate::transform::Translate;
+
+    // Free paths come from each path's own samples, so how blocks are spread over threads
+    // cannot change the image, also for media moved by a transform.
+    #[test]
+    fn media_render_the_same_on_any_number_of_threads() {
+        let mut world = Scene::new();
+        let boundary =
+            |center| Sphere::new(center, 0.5, Isotropic::new(SolidColour::new(Colour::WHITE)));
+        world.add(ConstantMedium::isotropic(
+            boundary(Point3::new(-0.5, 0.0, -1.0)),
+            2.0,
+            Colour::new(0.8, 0.8, 0.8),
+        ));
+        world.add(Translate::new(
+            ConstantMedium::isotropic(boundary(Point3::ZERO), 2.0, Colour::new(0.8, 0.4, 0.2)),
+            Vec3::new(0.5, 0.0, -1.0),
+        ));
+        let cam = Camera {
+            image_width: 32,
+            ..Camera::default()
+        };
+        let mut render = |threads| {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap();
+            pool.install(|| {
+                cam.renderer(4, 8)
+                    .with_progress(|_: &ProgressEvent| {})
+                    .render_frame(&mut world)
+                    .unwrap()
+                    .image
+            })
+        };
+        assert_eq!(render(1), render(4));
+    }
 }